@@ -0,0 +1,2867 @@
+//! Sampling engine behind the random-distributions-cli command-line tool.
+//! [`Distributions`] describes a distribution with its parameters, it can be turned to a
+//! [`DistributionObject`] using [`build_distribution`] and then sampled, e.g. using [`SampleStream`].
+
+use std::f64::consts::PI;
+use std::f64::consts::FRAC_PI_2;
+use std::f64::consts::FRAC_2_PI;
+
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand)]
+pub enum Distributions {
+    Uniform(Uniform),
+    Normal(Normal),
+    LogNormal(LogNormal),
+    LogitNormal(LogitNormal),
+    SkewNormal(SkewNormal),
+    TruncatedNormal(TruncatedNormal),
+    GenNormal(GenNormal),
+    HalfNormal(HalfNormal),
+    Cauchy(Cauchy),
+    HalfCauchy(HalfCauchy),
+    Triangular(Triangular),
+    StudentsT(StudentsT),
+    NoncentralT(NoncentralT),
+    Stable(Stable),
+    Empirical(Empirical),
+    Bootstrap(Bootstrap),
+    EmpiricalCdf(EmpiricalCdf),
+    Categorical(Categorical),
+    Benford(Benford),
+    Beta(Beta),
+    BetaPrime(BetaPrime),
+    Bernoulli(Bernoulli),
+    Binomial(Binomial),
+    NegativeBinomial(NegativeBinomial),
+    GammaPoisson(GammaPoisson),
+    DiscreteUniform(DiscreteUniform),
+    DiceUniform(DiceUniform),
+    ChiSquared(ChiSquared),
+    NoncentralChiSquared(NoncentralChiSquared),
+    Chi(Chi),
+    Gamma(Gamma),
+    Exp(Exp),
+    FisherSnedecor(FisherSnedecor),
+    F(F),
+    Geometric(Geometric),
+    Hypergeometric(Hypergeometric),
+    InverseGamma(InverseGamma),
+    InvGamma(InvGamma),
+    Wald(Wald),
+    InverseGaussian(InverseGaussian),
+    Laplace(Laplace),
+    TukeyLambda(TukeyLambda),
+    Pareto(Pareto),
+    Poisson(Poisson),
+    ZtPoisson(ZtPoisson),
+    Weibull(Weibull),
+    Gumbel(Gumbel),
+    Rayleigh(Rayleigh),
+    Maxwell(Maxwell),
+    Planck(Planck),
+    Frechet(Frechet),
+    VonMises(VonMises),
+    Zipf(Zipf),
+    Zeta(Zeta),
+    Dirichlet(Dirichlet),
+    Multinomial(Multinomial),
+    UnitCircle(UnitCircle),
+    Gaussian2d(Gaussian2d),
+    UnitDisc(UnitDisc),
+    UnitSphere(UnitSphere),
+    UnitBall(UnitBall),
+    Mixture(Mixture),
+    Interleave(Interleave),
+    Constant(Constant),
+}
+
+impl Distributions {
+    /// Whether all samples of the distribution are whole numbers
+    pub fn is_integer_valued(&self) -> bool {
+        if let Distributions::Constant(Constant { value }) = self {
+            return value.fract() == 0.0;
+        }
+        if let Distributions::Mixture(Mixture { components }) = self {
+            return components.iter().all(|c| {
+                parse_mixture_component(c).is_ok_and(|(_, d)| d.is_integer_valued())
+            });
+        }
+        if let Distributions::Interleave(Interleave { distributions }) = self {
+            return distributions.iter().all(|spec| parse_spec(spec).is_ok_and(|d| d.is_integer_valued()));
+        }
+        matches!(self,
+            Distributions::Categorical(_) |
+            Distributions::Benford(_) |
+            Distributions::Bernoulli(_) |
+            Distributions::Binomial(_) |
+            Distributions::NegativeBinomial(_) |
+            Distributions::GammaPoisson(_) |
+            Distributions::DiscreteUniform(_) |
+            Distributions::DiceUniform(_) |
+            Distributions::Geometric(_) |
+            Distributions::Hypergeometric(_) |
+            Distributions::Poisson(_) |
+            Distributions::ZtPoisson(_) |
+            Distributions::Zipf(_) |
+            Distributions::Zeta(_) |
+            Distributions::Multinomial(_)
+        )
+    }
+}
+
+/// Distribution that generates one value per sample
+pub trait DistributionObject {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64;
+
+    /// Whether this is a compound distribution with a latent variable drawn for each sample
+    fn has_latent(&self) -> bool {
+        false
+    }
+
+    /// Draw a sample together with its latent variable, which is NaN if [`DistributionObject::has_latent`] is false
+    fn sample_with_latent(&self, rng: &mut dyn rand::RngCore) -> (f64, f64) {
+        (self.sample(rng), f64::NAN)
+    }
+
+    /// Forget the state kept between samples, e.g. the rest of a bootstrap block,
+    /// so that following samples depend only on the random numbers drawn from then on
+    fn reset(&self) {}
+}
+impl<T: rand::distributions::Distribution<f64>> DistributionObject for T {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        rand::distributions::Distribution::sample(self, rng)
+    }
+}
+
+/// Distribution that generates multiple values (a vector) per sample
+pub trait MultiDistributionObject {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64>;
+
+    /// Number of values in each sample
+    fn dimensions(&self) -> usize;
+
+    /// Same as [`DistributionObject::reset`]
+    fn reset(&self) {}
+}
+
+/// Inverse of the cumulative distribution function, mapping probabilities from 0 to 1 to values of a distribution
+pub trait QuantileFunction {
+    fn inverse_cdf(&self, p: f64) -> f64;
+}
+impl<F: Fn(f64) -> f64> QuantileFunction for F {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self(p)
+    }
+}
+
+/// Scalar or vector-valued distribution, as built by [`build_sampler`]
+pub enum Sampler {
+    Scalar(Box<dyn DistributionObject>),
+    Vector(Box<dyn MultiDistributionObject>),
+}
+
+impl Sampler {
+    /// Turn a compound distribution into a vector-valued one, generating each sample followed by its latent variable
+    pub fn with_latent(self) -> anyhow::Result<Sampler> {
+        match self {
+            Sampler::Scalar(d) if d.has_latent() => Ok(Sampler::Vector(Box::new(WithLatent(d)))),
+            _ => anyhow::bail!("Distribution has no latent variable"),
+        }
+    }
+
+    /// Forget the state kept between samples, see [`DistributionObject::reset`]
+    pub fn reset(&self) {
+        match self {
+            Sampler::Scalar(d) => d.reset(),
+            Sampler::Vector(d) => d.reset(),
+        }
+    }
+}
+
+struct WithLatent(Box<dyn DistributionObject>);
+
+impl MultiDistributionObject for WithLatent {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64> {
+        let (x, latent) = self.0.sample_with_latent(rng);
+        vec![x, latent]
+    }
+
+    fn dimensions(&self) -> usize {
+        2
+    }
+
+    fn reset(&self) {
+        self.0.reset();
+    }
+}
+
+/// Adapter for distributions that generate integers instead of floats
+struct IntegerDistribution<T>(T);
+impl<T: rand::distributions::Distribution<u64>> DistributionObject for IntegerDistribution<T> {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        rand::distributions::Distribution::sample(&self.0, rng) as f64
+    }
+}
+
+/// Uniform distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="uniform")]
+pub struct Uniform {
+    /// include specified maximum value as possible candidate for generation
+    #[argh(switch)]
+    pub right_inclusive: bool,
+
+    #[argh(positional)]
+    pub min: f64,
+
+    #[argh(positional)]
+    pub max: f64,
+}
+
+/// Normal, Gaussian distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="normal")]
+pub struct Normal {
+    #[argh(positional)]
+    pub mean: f64,
+
+    #[argh(positional)]
+    pub std_dev: f64,
+}
+
+/// Log-normal distribution - exponent of a normal distribution with specified location and scale
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="lognormal")]
+pub struct LogNormal {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Logit-normal distribution - logistic function 1/(1+e^-x) of a normal distribution with specified location and scale,
+/// generating values between 0 and 1, e.g. proportions
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="logitnormal")]
+pub struct LogitNormal {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Skew-normal distribution - normal distribution with asymmetry controlled by the shape parameter
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="skewnormal")]
+pub struct SkewNormal {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+/// Truncated normal distribution - normal distribution restricted to the interval from lo to hi
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="truncatednormal")]
+pub struct TruncatedNormal {
+    #[argh(positional)]
+    pub mean: f64,
+
+    #[argh(positional)]
+    pub std_dev: f64,
+
+    #[argh(positional)]
+    pub lo: f64,
+
+    #[argh(positional)]
+    pub hi: f64,
+}
+
+/// Generalized normal (exponential power) distribution - Laplace distribution for shape 1, normal for shape 2,
+/// approaching uniform for large shapes
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="gennormal")]
+pub struct GenNormal {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+/// Half-normal distribution - absolute value of a normal distribution with zero mean and specified scale
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="halfnormal")]
+pub struct HalfNormal {
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Cauchy, Lorentz distribution - fat-tailed and continuous
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="cauchy")]
+pub struct Cauchy {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+
+/// Half-Cauchy distribution - absolute value of a Cauchy distribution with zero location and specified scale
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="halfcauchy")]
+pub struct HalfCauchy {
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Triangular distribution - continuous
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="triangular")]
+pub struct Triangular {
+    #[argh(positional)]
+    pub min: f64,
+
+
+    #[argh(positional)]
+    pub mode: f64,
+
+    #[argh(positional)]
+    pub max: f64,
+}
+
+
+/// Student-T distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="studentt")]
+pub struct StudentsT {
+    #[argh(positional)]
+    pub location: f64,
+
+
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub freedom: f64,
+}
+
+/// Noncentral Student-T distribution with noncentrality parameter `ncp`, e.g. for power analysis of t-tests
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="noncentralt")]
+pub struct NoncentralT {
+    #[argh(positional)]
+    pub freedom: f64,
+
+    #[argh(positional)]
+    pub ncp: f64,
+}
+
+/// General case of stable continuous distribution, generated by CMS method.
+/// Note that version 0.1 of this program used nonstandard distribution when alpha was not 1.0.
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="stable")]
+pub struct Stable {
+    #[argh(positional)]
+    pub location: f64,
+
+
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub alpha: f64,
+
+    #[argh(positional)]
+    pub beta: f64,
+}
+
+
+/// Discrete distribution that just endlessly randomly selects one of specified values
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="empirical")]
+pub struct Empirical {
+    /// read additional whitespace-separated data points from the specified file, `-` means stdin
+    #[argh(option)]
+    pub from_file: Option<std::path::PathBuf>,
+
+    /// comma-separated weights of data points, e.g. for importance resampling. Values are selected with probabilities
+    /// proportional to their weights instead of uniformly
+    #[argh(option)]
+    pub weights: Option<String>,
+
+    #[argh(positional)]
+    pub data_points: Vec<f64>,
+}
+
+
+/// Bootstrap resampling of data points with replacement. With --block, consecutive runs of data points starting
+/// at random positions are output (moving-block bootstrap), which keeps short-range dependence of time series
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="bootstrap")]
+pub struct Bootstrap {
+    /// read additional whitespace-separated data points from the specified file, `-` means stdin
+    #[argh(option)]
+    pub from_file: Option<std::path::PathBuf>,
+
+    /// number of consecutive data points in each resampled block. Default is 1, i.e. independent resampling
+    #[argh(option, default="1")]
+    pub block: usize,
+
+    #[argh(positional)]
+    pub data_points: Vec<f64>,
+}
+
+/// Continuous distribution with piecewise-linear CDF through tabulated points, e.g. measured quantiles.
+/// Values are uniformly distributed between consecutive points
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="empiricalcdf")]
+pub struct EmpiricalCdf {
+    /// read whitespace-separated pairs of value and cumulative probability from the specified file, `-` means stdin.
+    /// Probabilities must increase from 0 to 1, values must not decrease
+    #[argh(option)]
+    pub from_file: std::path::PathBuf,
+}
+
+/// Discrete distribution that generates values according to specified probabilities 
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="categorical")]
+pub struct Categorical {
+    /// read additional probabilities from the specified file, one per line, `-` means stdin
+    #[argh(option)]
+    pub from_file: Option<std::path::PathBuf>,
+
+    /// comma-separated names of the categories to output instead of their indices
+    #[argh(option)]
+    pub labels: Option<String>,
+
+    #[argh(positional)]
+    pub probabilities: Vec<f64>,
+}
+
+/// Benford's law - generates leading digits from 1 to 9, digit d with probability log10(1+1/d)
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="benford")]
+pub struct Benford {
+}
+
+/// Beta distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="beta")]
+pub struct Beta {
+    #[argh(positional)]
+    pub shape_a: f64,
+
+    #[argh(positional)]
+    pub shape_b: f64,
+}
+
+/// Beta prime distribution - odds x/(1-x) of a Beta distributed x
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="betaprime")]
+pub struct BetaPrime {
+    #[argh(positional)]
+    pub alpha: f64,
+
+    #[argh(positional)]
+    pub beta: f64,
+}
+
+/// Bernoulli distribution - generates 1 with specified probability, 0 otherwise
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="bernoulli")]
+pub struct Bernoulli {
+    #[argh(positional)]
+    pub probability: f64,
+}
+
+/// Binomial distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="binomial")]
+pub struct Binomial {
+    #[argh(positional)]
+    pub probability: f64,
+
+    #[argh(positional)]
+    pub trials: u64,
+}
+
+/// Negative binomial distribution - number of failures before specified number of successes
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="negbinomial")]
+pub struct NegativeBinomial {
+    #[argh(positional)]
+    pub successes: f64,
+
+    #[argh(positional)]
+    pub probability: f64,
+}
+
+/// Gamma-Poisson (Polya) distribution - Poisson counts with lambda drawn from gamma distribution for each sample.
+/// Same as negative binomial with `shape` successes and probability rate/(rate+1), but generated explicitly as a compound
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="gammapoisson")]
+pub struct GammaPoisson {
+    #[argh(positional)]
+    pub shape: f64,
+
+    #[argh(positional)]
+    pub rate: f64,
+}
+
+/// Discrete uniform, generates integer numbers from min to max, both inclusive
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="discreteuniform")]
+pub struct DiscreteUniform {
+    #[argh(positional)]
+    pub min: i64,
+
+    #[argh(positional)]
+    pub max: i64,
+}
+
+/// Alias for discreteuniform
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="diceuniform")]
+pub struct DiceUniform {
+    #[argh(positional)]
+    pub min: i64,
+
+    #[argh(positional)]
+    pub max: i64,
+}
+
+/// Chi-squared distribution which is a special case of the Gamma distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="chisquared")]
+pub struct ChiSquared {
+    #[argh(positional)]
+    pub freedom: f64,
+}
+
+/// Noncentral chi-squared distribution - sum of squares of `freedom` normal components with unit variance,
+/// whose squared means sum up to `ncp`
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="noncentralchi2")]
+pub struct NoncentralChiSquared {
+    #[argh(positional)]
+    pub freedom: f64,
+
+    #[argh(positional)]
+    pub ncp: f64,
+}
+
+
+/// Chi distribution - square root of chi-squared distribution, e.g. magnitude of a vector of `freedom` standard normal components
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="chi")]
+pub struct Chi {
+    #[argh(positional)]
+    pub freedom: f64,
+}
+
+/// Gamma distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="gamma")]
+pub struct Gamma {
+    #[argh(positional)]
+    pub shape: f64,
+
+    #[argh(positional)]
+    pub rate: f64,
+}
+
+
+/// Exponential distribution and is a special case of the Gamma distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="exp")]
+pub struct Exp {
+    #[argh(positional)]
+    pub rate: f64,
+}
+
+/// Fisher-Snedecor distribution also commonly known as the F-distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="fisher")]
+pub struct FisherSnedecor {
+    #[argh(positional)]
+    pub freedom_1: f64,
+
+    #[argh(positional)]
+    pub freedom_2: f64,
+}
+
+/// Alias for fisher
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="f")]
+pub struct F {
+    #[argh(positional)]
+    pub freedom_1: f64,
+
+    #[argh(positional)]
+    pub freedom_2: f64,
+}
+
+/// Geometric distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="geometric")]
+pub struct Geometric {
+    #[argh(positional)]
+    pub probability: f64,
+}
+
+/// Hypergeometric distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="hypergeometric")]
+pub struct Hypergeometric {
+    #[argh(positional)]
+    pub population: u64,
+
+    #[argh(positional)]
+    pub successes: u64,
+
+    #[argh(positional)]
+    pub draws: u64,
+}
+
+/// Inverse Gamma distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="inversegamma")]
+pub struct InverseGamma {
+    #[argh(positional)]
+    pub shape: f64,
+
+    #[argh(positional)]
+    pub rate: f64,
+}
+
+/// Alias for inversegamma
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="invgamma")]
+pub struct InvGamma {
+    #[argh(positional)]
+    pub shape: f64,
+
+    #[argh(positional)]
+    pub rate: f64,
+}
+
+/// Wald, inverse Gaussian distribution - first passage time of Brownian motion with drift
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="wald")]
+pub struct Wald {
+    #[argh(positional)]
+    pub mean: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+/// Alias for wald
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="inversegaussian")]
+pub struct InverseGaussian {
+    #[argh(positional)]
+    pub mean: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+/// Laplace distribution.
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="laplace")]
+pub struct Laplace {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Tukey lambda distribution - symmetric family defined by its quantile function (u^lambda - (1-u)^lambda)/lambda.
+/// lambda=0 is logistic, lambda=0.14 is close to normal, lambda=1 and lambda=2 are uniform, negative lambda has heavy tails
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="tukeylambda")]
+pub struct TukeyLambda {
+    #[argh(positional)]
+    pub lambda: f64,
+}
+
+/// Pareto distribution - heavy-tailed, all samples are at least `scale`
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="pareto")]
+pub struct Pareto {
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+
+/// Poisson distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="poisson")]
+pub struct Poisson {
+    #[argh(positional)]
+    pub lambda: f64,
+}
+
+/// Zero-truncated Poisson distribution - Poisson distribution conditioned on being at least 1,
+/// e.g. counts of events where zeros cannot be observed
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="ztpoisson")]
+pub struct ZtPoisson {
+    #[argh(positional)]
+    pub lambda: f64,
+}
+
+/// Weibull distribution
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="weibull")]
+pub struct Weibull {
+    #[argh(positional)]
+    pub shape: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Gumbel distribution - extreme value type I
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="gumbel")]
+pub struct Gumbel {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Rayleigh distribution - magnitude of a 2D vector of normally distributed components
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="rayleigh")]
+pub struct Rayleigh {
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Maxwell-Boltzmann distribution - magnitude of a 3D vector of normally distributed components,
+/// e.g. speeds of gas particles
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="maxwell")]
+pub struct Maxwell {
+    #[argh(positional)]
+    pub scale: f64,
+}
+
+/// Frechet distribution - extreme value type II, heavy-tailed
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="frechet")]
+pub struct Frechet {
+    #[argh(positional)]
+    pub location: f64,
+
+    #[argh(positional)]
+    pub scale: f64,
+
+    #[argh(positional)]
+    pub shape: f64,
+}
+
+/// Von Mises distribution - circular analogue of the normal distribution, generates angles in (-pi, pi]
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="vonmises")]
+pub struct VonMises {
+    #[argh(positional)]
+    pub mu: f64,
+
+    /// concentration, 0 means uniform distribution on the circle
+    #[argh(positional)]
+    pub kappa: f64,
+}
+
+/// Zipf distribution - generates ranks from 1 to n with probability inversely proportional to a power of the rank
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="zipf")]
+pub struct Zipf {
+    #[argh(positional)]
+    pub n: u64,
+
+    #[argh(positional)]
+    pub exponent: f64,
+}
+
+/// Planck distribution - density proportional to x^a/(e^x - 1), e.g. photon energies of black-body radiation for a=3
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="planck")]
+pub struct Planck {
+    #[argh(positional)]
+    pub a: f64,
+}
+
+/// Zeta distribution - Zipf distribution with unlimited number of ranks
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="zeta")]
+pub struct Zeta {
+    #[argh(positional)]
+    pub exponent: f64,
+}
+
+/// Dirichlet distribution - generates vectors of components that sum up to 1, one vector per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="dirichlet")]
+pub struct Dirichlet {
+    #[argh(positional)]
+    pub concentrations: Vec<f64>,
+}
+
+/// Multinomial distribution - generates counts of each category after specified number of trials, one vector per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="multinomial")]
+pub struct Multinomial {
+    #[argh(positional)]
+    pub trials: u64,
+
+    #[argh(positional)]
+    pub probabilities: Vec<f64>,
+}
+
+/// Points uniformly distributed on the unit circle, two columns (x, y) per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="unitcircle")]
+pub struct UnitCircle {
+}
+
+/// Points uniformly distributed inside the unit disc, two columns (x, y) per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="disc")]
+pub struct UnitDisc {
+}
+
+/// Points uniformly distributed on the unit sphere, i.e. random 3D directions. Three columns (x, y, z) per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="sphere")]
+pub struct UnitSphere {
+}
+
+/// Points uniformly distributed inside the unit ball, three columns (x, y, z) per line
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="ball")]
+pub struct UnitBall {
+}
+
+/// Circularly symmetric 2D normal distribution, e.g. complex Gaussian noise. Two columns (re, im) per line,
+/// each with the specified standard deviation
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="gaussian2d")]
+pub struct Gaussian2d {
+    #[argh(positional)]
+    pub std_dev: f64,
+}
+
+/// Mixture of distributions - each sample comes from one of the components, selected according to their weights.
+/// Components are specified like `0.3:normal:0:1`, i.e. weight, subcommand name and its arguments separated by colons
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="mixture")]
+pub struct Mixture {
+    #[argh(positional)]
+    pub components: Vec<String>,
+}
+
+/// Samples of the specified distributions in turn, e.g. the first, third and fifth from `normal:0:1` and the second,
+/// fourth and sixth from `normal:0:10` for `interleave normal:0:1 normal:0:10`.
+/// Distributions are specified as subcommand name and its arguments separated by colons.
+/// Samples discarded by -L/-H are not replaced by the same distribution, which shifts the order
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="interleave")]
+pub struct Interleave {
+    #[argh(positional)]
+    pub distributions: Vec<String>,
+}
+
+/// Degenerate distribution that always generates the specified value
+#[derive(argh::FromArgs, Clone)]
+#[argh(subcommand, name="constant")]
+pub struct Constant {
+    #[argh(positional)]
+    pub value: f64,
+}
+
+struct StableAlphaNotOne {
+    location: f64,
+    alpha: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+    w_dist: statrs::distribution::Exp,
+    calc_scale: f64,
+    xi: f64,
+    alpha_inv: f64,
+    alpha2: f64,
+}
+
+impl StableAlphaNotOne {
+    pub fn new(location: f64, scale: f64, alpha: f64, beta: f64) -> Self {
+        let zeta = -beta * (FRAC_PI_2*alpha).tan();
+        Self {
+            location,
+            alpha,
+            u_dist: rand::distributions::Uniform::new(-FRAC_PI_2 + 3.0*f64::EPSILON, FRAC_PI_2 ),
+            w_dist: statrs::distribution::Exp::new(1.0).unwrap(),
+            calc_scale: (zeta*zeta+1.0).powf(0.5/alpha)*scale,
+            xi: (-zeta).atan() / alpha,
+            alpha_inv: 1.0/alpha,
+            alpha2: (1.0 - alpha)/alpha,
+        }
+    }
+}
+
+/// Implementation is based on https://en.wikipedia.org/w/index.php?title=Stable_distribution&oldid=1025369901
+impl DistributionObject for StableAlphaNotOne {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let u = self.u_dist.sample(rng);
+        let w = self.w_dist.sample(rng);
+        let num1 = (self.alpha*(u + self.xi)).sin();
+        let den1 = u.cos().powf(self.alpha_inv);
+        let num2 = (u - self.alpha * (u + self.xi)).cos() / w;
+        self.location + self.calc_scale * num1 / den1 * (num2).powf(self.alpha2)
+    }
+}
+
+struct StableAlphaOne {
+    location: f64,
+    beta: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+    w_dist: statrs::distribution::Exp,
+    calc_scale: f64,
+}
+
+impl StableAlphaOne {
+    pub fn new(location: f64, scale: f64, beta: f64) -> Self {
+        //scale *= std::f64::consts::FRAC_1_SQRT_2;
+        Self {
+            location: location + FRAC_2_PI * beta * scale * scale.ln(),
+            beta,
+            u_dist: rand::distributions::Uniform::new(-FRAC_PI_2 + 3.0*f64::EPSILON, FRAC_PI_2 ),
+            w_dist: statrs::distribution::Exp::new(1.0).unwrap(),
+            calc_scale: scale * FRAC_2_PI,
+        }
+    }
+}
+
+/// Implementation is based on https://en.wikipedia.org/w/index.php?title=Stable_distribution&oldid=1025369901
+impl DistributionObject for StableAlphaOne {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let u = self.u_dist.sample(rng);
+        let w = self.w_dist.sample(rng);
+        self.location + self.calc_scale * ( (FRAC_PI_2 + self.beta * u) * u.tan() - self.beta * ( (FRAC_PI_2 * w * u.cos())/(FRAC_PI_2 + self.beta*u) ).ln() )
+    }
+}
+
+/// Own implementation instead of statrs's one due to https://github.com/statrs-dev/statrs/issues/150
+struct LaplaceInverseCdf {
+    location: f64,
+    scale: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl LaplaceInverseCdf {
+    pub fn new(location: f64, scale: f64) -> anyhow::Result<Self> {
+        positive("scale", scale)?;
+        Ok(Self {
+            location,
+            scale,
+            u_dist: rand::distributions::Uniform::new(-0.5 + f64::EPSILON, 0.5),
+        })
+    }
+}
+
+impl DistributionObject for LaplaceInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let u = self.u_dist.sample(rng);
+        self.location - self.scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+    }
+}
+
+impl QuantileFunction for LaplaceInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        if p < 0.5 {
+            self.location + self.scale * (2.0 * p).ln()
+        } else {
+            self.location - self.scale * (2.0 - 2.0 * p).ln()
+        }
+    }
+}
+struct TukeyLambdaInverseCdf {
+    lambda: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl TukeyLambdaInverseCdf {
+    pub fn new(lambda: f64) -> anyhow::Result<Self> {
+        if !lambda.is_finite() {
+            anyhow::bail!("lambda must be finite, got {}", lambda);
+        }
+        Ok(Self {
+            lambda,
+            u_dist: rand::distributions::Uniform::new(f64::EPSILON, 1.0),
+        })
+    }
+}
+
+impl DistributionObject for TukeyLambdaInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.inverse_cdf(self.u_dist.sample(rng))
+    }
+}
+
+impl QuantileFunction for TukeyLambdaInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        if self.lambda == 0.0 {
+            (p / (1.0 - p)).ln()
+        } else {
+            (p.powf(self.lambda) - (1.0 - p).powf(self.lambda)) / self.lambda
+        }
+    }
+}
+
+struct GumbelInverseCdf {
+    location: f64,
+    scale: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl GumbelInverseCdf {
+    pub fn new(location: f64, scale: f64) -> anyhow::Result<Self> {
+        positive("scale", scale)?;
+        Ok(Self {
+            location,
+            scale,
+            u_dist: rand::distributions::Uniform::new(f64::EPSILON, 1.0),
+        })
+    }
+}
+
+impl DistributionObject for GumbelInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.inverse_cdf(self.u_dist.sample(rng))
+    }
+}
+
+impl QuantileFunction for GumbelInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self.location - self.scale * (-p.ln()).ln()
+    }
+}
+struct RayleighInverseCdf {
+    scale: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl RayleighInverseCdf {
+    pub fn new(scale: f64) -> anyhow::Result<Self> {
+        positive("scale", scale)?;
+        Ok(Self {
+            scale,
+            u_dist: rand::distributions::Uniform::new_inclusive(f64::EPSILON, 1.0),
+        })
+    }
+}
+
+impl DistributionObject for RayleighInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let u = self.u_dist.sample(rng);
+        self.scale * (-2.0 * u.ln()).sqrt()
+    }
+}
+
+impl QuantileFunction for RayleighInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self.scale * (-2.0 * (-p).ln_1p()).sqrt()
+    }
+}
+/// Samples uniformly between Phi(lo) and Phi(hi) and inverts it.
+/// Intervals above the mean are mirrored to the lower tail, where the CDF does not lose precision.
+struct TruncatedNormalInverseCdf {
+    mean: f64,
+    std_dev: f64,
+    mirrored: bool,
+    pa: f64,
+    pb: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl TruncatedNormalInverseCdf {
+    pub fn new(mean: f64, std_dev: f64, lo: f64, hi: f64) -> anyhow::Result<Self> {
+        positive("std_dev", std_dev)?;
+        if lo.is_nan() || hi.is_nan() || lo >= hi {
+            anyhow::bail!("lo must be below hi");
+        }
+        let mut a = (lo - mean) / std_dev;
+        let mut b = (hi - mean) / std_dev;
+        let mirrored = a > 0.0;
+        if mirrored {
+            let t = a;
+            a = -b;
+            b = -t;
+        }
+        let phi = |x: f64| 0.5 * statrs::function::erf::erfc(-x / std::f64::consts::SQRT_2);
+        let (pa, pb) = (phi(a), phi(b));
+        if pa.is_nan() || pb.is_nan() || pa >= pb {
+            anyhow::bail!("interval is too far in the tail of the distribution");
+        }
+        Ok(Self {
+            mean,
+            std_dev,
+            mirrored,
+            pa,
+            pb,
+            u_dist: rand::distributions::Uniform::new(pa, pb),
+        })
+    }
+
+    fn invert_phi(&self, u: f64) -> f64 {
+        let z = -std::f64::consts::SQRT_2 * statrs::function::erf::erfc_inv(2.0 * u);
+        let z = if self.mirrored { -z } else { z };
+        self.mean + self.std_dev * z
+    }
+}
+
+impl DistributionObject for TruncatedNormalInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.invert_phi(self.u_dist.sample(rng))
+    }
+}
+
+impl QuantileFunction for TruncatedNormalInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        let p = if self.mirrored { 1.0 - p } else { p };
+        self.invert_phi(self.pa + p * (self.pb - self.pa))
+    }
+}
+
+/// |x - location| / scale raised to the shape power is Gamma(1/shape, 1) distributed
+struct GenNormalGamma {
+    location: f64,
+    scale: f64,
+    shape_inv: f64,
+    gamma: statrs::distribution::Gamma,
+}
+
+impl GenNormalGamma {
+    pub fn new(location: f64, scale: f64, shape: f64) -> anyhow::Result<Self> {
+        Ok(Self {
+            location,
+            scale,
+            shape_inv: 1.0 / shape,
+            gamma: statrs::distribution::Gamma::new(1.0 / shape, 1.0)?,
+        })
+    }
+}
+
+impl DistributionObject for GenNormalGamma {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let r = self.scale * self.gamma.sample(rng).powf(self.shape_inv);
+        if rand::Rng::gen::<bool>(rng) {
+            self.location + r
+        } else {
+            self.location - r
+        }
+    }
+}
+
+struct GammaPoissonCompound {
+    gamma: statrs::distribution::Gamma,
+}
+
+impl DistributionObject for GammaPoissonCompound {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.sample_with_latent(rng).0
+    }
+
+    fn has_latent(&self) -> bool {
+        true
+    }
+
+    /// Latent variable is lambda of the Poisson distribution
+    fn sample_with_latent(&self, rng: &mut dyn rand::RngCore) -> (f64, f64) {
+        let lambda = self.gamma.sample(rng);
+        // Gamma samples of small shape can underflow to zero, which Poisson does not accept
+        let x = match statrs::distribution::Poisson::new(lambda) {
+            Ok(poisson) => poisson.sample(rng),
+            Err(_) => 0.0,
+        };
+        (x, lambda)
+    }
+}
+
+struct MaxwellChiSquared {
+    scale: f64,
+    chi_squared: statrs::distribution::ChiSquared,
+}
+
+impl MaxwellChiSquared {
+    pub fn new(scale: f64) -> anyhow::Result<Self> {
+        Ok(Self {
+            scale,
+            chi_squared: statrs::distribution::ChiSquared::new(3.0)?,
+        })
+    }
+}
+
+impl DistributionObject for MaxwellChiSquared {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.scale * self.chi_squared.sample(rng).sqrt()
+    }
+}
+
+/// (Z + ncp) / sqrt(V / freedom) with standard normal Z and chi-squared V
+struct NoncentralTNormalChiSquared {
+    ncp: f64,
+    freedom: f64,
+    normal: statrs::distribution::Normal,
+    chi_squared: statrs::distribution::ChiSquared,
+}
+
+impl NoncentralTNormalChiSquared {
+    pub fn new(freedom: f64, ncp: f64) -> anyhow::Result<Self> {
+        Ok(Self {
+            ncp,
+            freedom,
+            normal: statrs::distribution::Normal::new(0.0, 1.0)?,
+            chi_squared: statrs::distribution::ChiSquared::new(freedom)?,
+        })
+    }
+}
+
+impl DistributionObject for NoncentralTNormalChiSquared {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        (self.normal.sample(rng) + self.ncp) / (self.chi_squared.sample(rng) / self.freedom).sqrt()
+    }
+}
+
+/// Central chi-squared with freedom + 2*J degrees of freedom, where J is Poisson with lambda ncp/2
+struct NoncentralChiSquaredPoisson {
+    freedom: f64,
+    poisson: Option<statrs::distribution::Poisson>,
+}
+
+impl NoncentralChiSquaredPoisson {
+    pub fn new(freedom: f64, ncp: f64) -> anyhow::Result<Self> {
+        Ok(Self {
+            freedom,
+            // Poisson does not accept lambda=0, which is the central case
+            poisson: if ncp > 0.0 { Some(statrs::distribution::Poisson::new(ncp / 2.0)?) } else { None },
+        })
+    }
+}
+
+impl DistributionObject for NoncentralChiSquaredPoisson {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let j = self.poisson.as_ref().map_or(0.0, |p| p.sample(rng));
+        statrs::distribution::ChiSquared::new(self.freedom + 2.0 * j).map_or(f64::NAN, |d| d.sample(rng))
+    }
+}
+
+/// x^a/(e^x - 1) is the sum of x^a*e^(-k*x) over k >= 1, i.e. a mixture of gamma distributions with shape a+1
+/// and rate k, whose weights 1/k^(a+1) are the zeta distribution
+struct PlanckZetaGamma {
+    zeta: rand_distr::Zeta<f64>,
+    gamma: statrs::distribution::Gamma,
+}
+
+impl PlanckZetaGamma {
+    pub fn new(a: f64) -> anyhow::Result<Self> {
+        positive("a", a)?;
+        // Otherwise the zeta exponent a+1 would be rounded to 1, where the zeta distribution does not exist
+        if !(1.0 + a > 1.0 && a.is_finite()) {
+            anyhow::bail!("a must be finite and not too close to 0, got {}", a);
+        }
+        Ok(Self {
+            zeta: rand_distr::Zeta::new(a + 1.0)?,
+            gamma: statrs::distribution::Gamma::new(a + 1.0, 1.0)?,
+        })
+    }
+}
+
+impl DistributionObject for PlanckZetaGamma {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let k = rand::distributions::Distribution::sample(&self.zeta, rng);
+        self.gamma.sample(rng) / k
+    }
+}
+
+/// Michael, Schucany and Haas method: transformed chi-squared draw, then choice between two roots
+struct WaldMichaelSchucanyHaas {
+    mean: f64,
+    shape: f64,
+    mean_over_2shape: f64,
+    normal: statrs::distribution::Normal,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl WaldMichaelSchucanyHaas {
+    pub fn new(mean: f64, shape: f64) -> Self {
+        Self {
+            mean,
+            shape,
+            mean_over_2shape: mean / (2.0 * shape),
+            normal: statrs::distribution::Normal::new(0.0, 1.0).unwrap(),
+            u_dist: rand::distributions::Uniform::new(0.0, 1.0),
+        }
+    }
+}
+
+impl DistributionObject for WaldMichaelSchucanyHaas {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let n : f64 = self.normal.sample(rng);
+        let my = self.mean * n * n;
+        let x = self.mean + self.mean_over_2shape * (my - (4.0 * self.shape * my + my * my).sqrt());
+        let u = self.u_dist.sample(rng);
+        if u <= self.mean / (self.mean + x) {
+            x
+        } else {
+            self.mean * self.mean / x
+        }
+    }
+}
+
+struct FrechetInverseCdf {
+    location: f64,
+    scale: f64,
+    shape_inv: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl FrechetInverseCdf {
+    pub fn new(location: f64, scale: f64, shape: f64) -> anyhow::Result<Self> {
+        positive("scale", scale)?;
+        positive("shape", shape)?;
+        Ok(Self {
+            location,
+            scale,
+            shape_inv: 1.0/shape,
+            u_dist: rand::distributions::Uniform::new(f64::EPSILON, 1.0),
+        })
+    }
+}
+
+impl DistributionObject for FrechetInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.inverse_cdf(self.u_dist.sample(rng))
+    }
+}
+
+impl QuantileFunction for FrechetInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self.location + self.scale * (-p.ln()).powf(-self.shape_inv)
+    }
+}
+struct VonMisesBestFisher {
+    mu: f64,
+    kappa: f64,
+    r: f64,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl VonMisesBestFisher {
+    /// Above this concentration the rejection loop loses precision, as r gets too close to 1,
+    /// while the normal approximation with variance 1/kappa is already accurate
+    const NORMAL_APPROXIMATION_KAPPA: f64 = 1e6;
+
+    pub fn new(mu: f64, kappa: f64) -> Self {
+        // hypot avoids overflow of 4*kappa^2
+        let tau = 1.0 + (2.0*kappa).hypot(1.0);
+        let rho = (tau - (2.0*tau).sqrt()) / (2.0*kappa);
+        Self {
+            mu,
+            kappa,
+            r: (1.0 + rho*rho) / (2.0*rho),
+            u_dist: rand::distributions::Uniform::new(0.0, 1.0),
+        }
+    }
+}
+
+/// Implementation is based on Best, D. J. and Fisher, N. I. (1979) "Efficient simulation of the von Mises distribution"
+impl DistributionObject for VonMisesBestFisher {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let theta = if self.kappa < 1e-9 {
+            self.u_dist.sample(rng) * 2.0 * PI
+        } else if self.kappa > Self::NORMAL_APPROXIMATION_KAPPA {
+            let z : f64 = rand_distr::StandardNormal.sample(rng);
+            self.mu + z / self.kappa.sqrt()
+        } else {
+            let f = loop {
+                let z = (PI * self.u_dist.sample(rng)).cos();
+                let f = (1.0 + self.r * z) / (self.r + z);
+                let c = self.kappa * (self.r - f);
+                let u2 = self.u_dist.sample(rng);
+                if c * (2.0 - c) > u2 || (c / u2).ln() + 1.0 >= c {
+                    break f;
+                }
+            };
+            let sign = if self.u_dist.sample(rng) < 0.5 { -1.0 } else { 1.0 };
+            self.mu + sign * f.clamp(-1.0, 1.0).acos()
+        };
+        let theta = theta.rem_euclid(2.0 * PI);
+        if theta > PI { theta - 2.0 * PI } else { theta }
+    }
+}
+
+struct DirichletGamma {
+    gammas: Vec<statrs::distribution::Gamma>,
+}
+
+impl DirichletGamma {
+    pub fn new(concentrations: &[f64]) -> anyhow::Result<Self> {
+        let gammas = concentrations.iter().map(|&a| statrs::distribution::Gamma::new(a, 1.0)).collect::<Result<_,_>>()?;
+        Ok(Self { gammas })
+    }
+}
+
+impl MultiDistributionObject for DirichletGamma {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64> {
+        let mut v : Vec<f64> = self.gammas.iter().map(|g| g.sample(rng)).collect();
+        let sum : f64 = v.iter().sum();
+        for x in v.iter_mut() { *x /= sum; }
+        v
+    }
+
+    fn dimensions(&self) -> usize {
+        self.gammas.len()
+    }
+}
+
+impl MultiDistributionObject for statrs::distribution::Multinomial {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64> {
+        rand::distributions::Distribution::sample(self, rng)
+    }
+
+    fn dimensions(&self) -> usize {
+        self.p().len()
+    }
+}
+
+/// Adapter for vector-valued distributions that generate fixed size arrays
+struct ArrayDistribution<T, const N: usize>(T);
+impl<T: rand::distributions::Distribution<[f64; N]>, const N: usize> MultiDistributionObject for ArrayDistribution<T, N> {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64> {
+        self.0.sample(rng).to_vec()
+    }
+
+    fn dimensions(&self) -> usize {
+        N
+    }
+}
+
+/// Uniformly distributed direction scaled by Rayleigh distributed radius
+struct Gaussian2dPolar {
+    radius: RayleighInverseCdf,
+}
+
+impl MultiDistributionObject for Gaussian2dPolar {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> Vec<f64> {
+        let [x, y] = rand::distributions::Distribution::<[f64; 2]>::sample(&rand_distr::UnitCircle, rng);
+        let r = self.radius.sample(rng);
+        vec![r * x, r * y]
+    }
+
+    fn dimensions(&self) -> usize {
+        2
+    }
+}
+
+/// Absolute value of a distribution symmetric around zero
+struct Folded<T>(T);
+impl<T: rand::distributions::Distribution<f64>> DistributionObject for Folded<T> {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.0.sample(rng).abs()
+    }
+}
+
+/// Logistic function of a distribution
+struct Sigmoid<T>(T);
+impl<T: rand::distributions::Distribution<f64>> DistributionObject for Sigmoid<T> {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        1.0 / (1.0 + (-self.0.sample(rng)).exp())
+    }
+}
+impl<T: statrs::distribution::ContinuousCDF<f64, f64>> QuantileFunction for Sigmoid<T> {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        1.0 / (1.0 + (-self.0.inverse_cdf(p)).exp())
+    }
+}
+
+/// Exponential function of a distribution, same as statrs's log-normal for a normal one
+struct Exponentiated<T>(T);
+impl<T: rand::distributions::Distribution<f64>> DistributionObject for Exponentiated<T> {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.0.sample(rng).exp()
+    }
+}
+impl<T: statrs::distribution::ContinuousCDF<f64, f64>> QuantileFunction for Exponentiated<T> {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self.0.inverse_cdf(p).exp()
+    }
+}
+
+/// Normal distribution underlying lognormal and logitnormal
+fn underlying_normal(location: f64, scale: f64) -> anyhow::Result<statrs::distribution::Normal> {
+    positive("scale", scale)?;
+    Ok(statrs::distribution::Normal::new(location, scale)?)
+}
+
+/// Same sampling as statrs's Pareto, which has no closed-form inverse CDF
+struct ParetoInverseCdf {
+    scale: f64,
+    shape_inv: f64,
+}
+
+impl ParetoInverseCdf {
+    pub fn new(scale: f64, shape: f64) -> anyhow::Result<Self> {
+        positive("scale", scale)?;
+        positive("shape", shape)?;
+        Ok(Self { scale, shape_inv: 1.0 / shape })
+    }
+}
+
+impl DistributionObject for ParetoInverseCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let u : f64 = rand::distributions::OpenClosed01.sample(rng);
+        self.scale * u.powf(-self.shape_inv)
+    }
+}
+
+impl QuantileFunction for ParetoInverseCdf {
+    fn inverse_cdf(&self, p: f64) -> f64 {
+        self.scale * (1.0 - p).powf(-self.shape_inv)
+    }
+}
+
+struct BetaPrimeOdds(statrs::distribution::Beta);
+impl DistributionObject for BetaPrimeOdds {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let x = self.0.sample(rng);
+        x / (1.0 - x)
+    }
+}
+
+/// Categorical distribution over digits 1 to 9
+struct BenfordDigits(statrs::distribution::Categorical);
+impl DistributionObject for BenfordDigits {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        // Categorical generates indices from 0
+        self.0.sample(rng) + 1.0
+    }
+}
+
+/// Values selected according to their weights
+struct WeightedValues {
+    values: Vec<f64>,
+    index: rand::distributions::WeightedIndex<f64>,
+}
+
+impl DistributionObject for WeightedValues {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.values[rand::distributions::Distribution::sample(&self.index, rng)]
+    }
+}
+
+/// Inverse of a piecewise-linear CDF by interpolation between tabulated points
+struct PiecewiseLinearCdf {
+    x: Vec<f64>,
+    p: Vec<f64>,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl PiecewiseLinearCdf {
+    pub fn from_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        let numbers = read_numbers(path)?;
+        if numbers.len() % 2 != 0 {
+            anyhow::bail!("{}: expected pairs of value and probability, got {} numbers", path.display(), numbers.len());
+        }
+        let (x, p) : (Vec<f64>, Vec<f64>) = numbers.chunks(2).map(|c| (c[0], c[1])).unzip();
+        if p.len() < 2 || p[0] != 0.0 || p[p.len() - 1] != 1.0 {
+            anyhow::bail!("{}: probabilities must start at 0 and end at 1", path.display());
+        }
+        if p.windows(2).any(|w| w[0] >= w[1]) {
+            anyhow::bail!("{}: probabilities must be increasing", path.display());
+        }
+        if x.iter().any(|x| !x.is_finite()) || x.windows(2).any(|w| w[0] > w[1]) {
+            anyhow::bail!("{}: values must be finite and must not decrease", path.display());
+        }
+        Ok(Self { x, p, u_dist: rand::distributions::Uniform::new(0.0, 1.0) })
+    }
+}
+
+impl QuantileFunction for PiecewiseLinearCdf {
+    fn inverse_cdf(&self, u: f64) -> f64 {
+        let i = self.p.partition_point(|&p| p <= u).clamp(1, self.p.len() - 1);
+        let t = (u - self.p[i - 1]) / (self.p[i] - self.p[i - 1]);
+        self.x[i - 1] + t * (self.x[i] - self.x[i - 1])
+    }
+}
+
+impl DistributionObject for PiecewiseLinearCdf {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.inverse_cdf(self.u_dist.sample(rng))
+    }
+}
+
+/// Moving-block bootstrap: outputs `block` consecutive values from a random start, then picks a new start
+struct BlockBootstrap {
+    values: Vec<f64>,
+    block: usize,
+    next: std::cell::Cell<usize>,
+    remaining: std::cell::Cell<usize>,
+    start_dist: rand::distributions::Uniform<usize>,
+}
+
+impl BlockBootstrap {
+    pub fn new(values: Vec<f64>, block: usize) -> Self {
+        Self {
+            start_dist: rand::distributions::Uniform::new_inclusive(0, values.len() - block),
+            values,
+            block,
+            next: std::cell::Cell::new(0),
+            remaining: std::cell::Cell::new(0),
+        }
+    }
+}
+
+impl DistributionObject for BlockBootstrap {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        if self.remaining.get() == 0 {
+            self.next.set(rand::distributions::Distribution::sample(&self.start_dist, rng));
+            self.remaining.set(self.block);
+        }
+        let i = self.next.get();
+        self.next.set(i + 1);
+        self.remaining.set(self.remaining.get() - 1);
+        self.values[i]
+    }
+
+    fn reset(&self) {
+        self.remaining.set(0);
+    }
+}
+
+/// Zero-truncated Poisson. Large lambda rarely generates zeros, so they are just rejected.
+/// For small lambda, the truncated CDF is inverted by sequential search, which takes few steps because the mean is small
+struct ZtPoissonSampler {
+    lambda: f64,
+    poisson: statrs::distribution::Poisson,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl ZtPoissonSampler {
+    pub fn new(lambda: f64) -> anyhow::Result<Self> {
+        Ok(Self {
+            lambda,
+            poisson: statrs::distribution::Poisson::new(lambda)?,
+            u_dist: rand::distributions::Uniform::new(0.0, 1.0),
+        })
+    }
+}
+
+impl DistributionObject for ZtPoissonSampler {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        if self.lambda >= 1.0 {
+            loop {
+                let x = self.poisson.sample(rng);
+                if x >= 1.0 {
+                    return x;
+                }
+            }
+        }
+        // P(k) = exp(-lambda) * lambda^k / k! / (1 - exp(-lambda)), scaled by the denominator
+        let u = self.u_dist.sample(rng) * -(-self.lambda).exp_m1();
+        let mut k = 1.0;
+        let mut p = self.lambda * (-self.lambda).exp();
+        let mut cdf = p;
+        while u > cdf && p > 0.0 {
+            k += 1.0;
+            p *= self.lambda / k;
+            cdf += p;
+        }
+        k
+    }
+}
+
+struct MixtureSampler {
+    components: Vec<Box<dyn DistributionObject>>,
+    selector: statrs::distribution::Categorical,
+}
+
+impl DistributionObject for MixtureSampler {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        self.sample_with_latent(rng).0
+    }
+
+    fn has_latent(&self) -> bool {
+        true
+    }
+
+    /// Latent variable is the index of the component, starting from 0
+    fn sample_with_latent(&self, rng: &mut dyn rand::RngCore) -> (f64, f64) {
+        let i = self.selector.sample(rng);
+        (self.components[i as usize].sample(rng), i)
+    }
+
+    fn reset(&self) {
+        self.components.iter().for_each(|c| c.reset());
+    }
+}
+
+struct InterleavedSampler {
+    distributions: Vec<Box<dyn DistributionObject>>,
+    next: std::cell::Cell<usize>,
+}
+
+impl DistributionObject for InterleavedSampler {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        let i = self.next.get();
+        self.next.set((i + 1) % self.distributions.len());
+        self.distributions[i].sample(rng)
+    }
+
+    fn reset(&self) {
+        self.next.set(0);
+        self.distributions.iter().for_each(|d| d.reset());
+    }
+}
+
+/// Error unless the parameter is positive, which also rules out NaN
+fn positive(name: &str, x: f64) -> anyhow::Result<f64> {
+    if x.is_nan() || x <= 0.0 {
+        anyhow::bail!("{} must be positive, got {}", name, x);
+    }
+    Ok(x)
+}
+
+/// Read whitespace-separated numbers from the specified file, `-` means stdin
+pub fn read_numbers(path: &std::path::Path) -> anyhow::Result<Vec<f64>> {
+    // stdin can be read only once, so all distributions and threads reading `-` share its numbers
+    static STDIN_NUMBERS : std::sync::OnceLock<Result<Vec<f64>, String>> = std::sync::OnceLock::new();
+    if path == std::path::Path::new("-") {
+        return STDIN_NUMBERS
+            .get_or_init(|| parse_numbers(path, Box::new(std::io::stdin().lock())).map_err(|e| format!("{:#}", e)))
+            .clone()
+            .map_err(anyhow::Error::msg);
+    }
+    let f = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))?;
+    parse_numbers(path, Box::new(std::io::BufReader::new(f)))
+}
+
+fn parse_numbers(path: &std::path::Path, reader: Box<dyn std::io::BufRead>) -> anyhow::Result<Vec<f64>> {
+    use std::io::BufRead;
+    let mut numbers = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        for word in line.split_whitespace() {
+            match word.parse() {
+                Ok(x) => numbers.push(x),
+                Err(_) => anyhow::bail!("{}:{}: invalid number `{}`", path.display(), i + 1, word),
+            }
+        }
+    }
+    Ok(numbers)
+}
+
+/// Parse distribution specified like `normal:0:1`, i.e. subcommand name and its arguments separated by colons
+pub fn parse_spec(spec: &str) -> anyhow::Result<Distributions> {
+    let mut parts = spec.split(':');
+    let name = parts.next().unwrap_or_default();
+    let args : Vec<&str> = parts.collect();
+    parse_args(name, &args).map_err(|e| anyhow::anyhow!("{} in `{}`", e, spec))
+}
+
+/// Parse distribution subcommand `name` with its arguments, e.g. `("normal", ["0", "1"])`
+pub fn parse_args(name: &str, args: &[&str]) -> anyhow::Result<Distributions> {
+    if !<Distributions as argh::SubCommands>::COMMANDS.iter().any(|c| c.name == name) {
+        anyhow::bail!("Unknown distribution `{}`", name);
+    }
+    let mut args = args.to_vec();
+    // Let negative numbers be positional arguments instead of options
+    if let Some(i) = args.iter().position(|p| p.starts_with('-') && p.parse::<f64>().is_ok()) {
+        args.insert(i, "--");
+    }
+    <Distributions as argh::FromArgs>::from_args(&[name], &args)
+        .map_err(|e| anyhow::anyhow!("Invalid arguments for `{}`: {}", name, e.output.trim()))
+}
+
+fn parse_mixture_component(component: &str) -> anyhow::Result<(f64, Distributions)> {
+    let (weight, spec) = component.split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Mixture component `{}` should look like weight:name:arguments", component))?;
+    let weight : f64 = weight.parse()
+        .map_err(|_| anyhow::anyhow!("Invalid weight in mixture component `{}`", component))?;
+    Ok((weight, parse_spec(spec)?))
+}
+
+pub fn build_distribution(distribution: Distributions) -> anyhow::Result<Box<dyn DistributionObject>> {
+    let d : Box<dyn DistributionObject> = match distribution {
+        Distributions::Uniform(Uniform { right_inclusive, min, max }) => {
+            if max <= min {
+                anyhow::bail!("Invalid distribution parameters");
+            }
+            if right_inclusive {
+                Box::new(rand::distributions::Uniform::new_inclusive(min, max))
+            } else {
+                Box::new(rand::distributions::Uniform::new(min, max))
+            }
+        }
+        Distributions::Normal(Normal { mean, std_dev }) => Box::new(statrs::distribution::Normal::new(mean, std_dev)?),
+        Distributions::LogNormal(LogNormal { location, scale }) => Box::new(Exponentiated(underlying_normal(location, scale)?)),
+        Distributions::LogitNormal(LogitNormal { location, scale }) => Box::new(Sigmoid(underlying_normal(location, scale)?)),
+        Distributions::SkewNormal(SkewNormal { location, scale, shape }) => {
+            positive("scale", scale)?;
+            Box::new(rand_distr::SkewNormal::new(location, scale, shape)?)
+        }
+        Distributions::Cauchy(Cauchy { location, scale }) => Box::new(statrs::distribution::Cauchy::new(location, scale)?),
+        Distributions::HalfNormal(HalfNormal { scale }) => {
+            positive("scale", scale)?;
+            Box::new(Folded(statrs::distribution::Normal::new(0.0, scale)?))
+        }
+        Distributions::HalfCauchy(HalfCauchy { scale }) => {
+            positive("scale", scale)?;
+            Box::new(Folded(statrs::distribution::Cauchy::new(0.0, scale)?))
+        }
+        Distributions::Triangular(Triangular { min, mode, max }) => {
+            if !(min <= mode && mode <= max && min < max) {
+                anyhow::bail!("Expected min <= mode <= max and min < max");
+            }
+            // Note that statrs takes mode as the last argument, unlike our command line
+            Box::new(statrs::distribution::Triangular::new(min,max,mode)?)
+        }
+        Distributions::StudentsT(StudentsT { location, scale, freedom }) =>  Box::new(statrs::distribution::StudentsT::new(location,scale,freedom)?),
+        Distributions::NoncentralT(NoncentralT { freedom, ncp }) => {
+            positive("freedom", freedom)?;
+            if !ncp.is_finite() {
+                anyhow::bail!("ncp must be finite, got {}", ncp);
+            }
+            Box::new(NoncentralTNormalChiSquared::new(freedom, ncp)?)
+        }
+        Distributions::Stable(Stable { location, scale, alpha, beta }) => {
+            if !(0.0..=2.0).contains(&alpha) {
+                anyhow::bail!("alpha must be between 0 and 2");
+            }
+            if !(-1.0..=1.0).contains(&beta) {
+                anyhow::bail!("beta must be between -1 and 1");
+            }
+            if alpha > 0.999 && alpha < 1.001 {
+                Box::new(StableAlphaOne::new(location,scale,beta))
+            } else {
+                Box::new(StableAlphaNotOne::new(location,scale,alpha,beta))
+            }
+        }
+        Distributions::Empirical(Empirical { from_file, weights, mut data_points }) => {
+            if let Some(path) = from_file {
+                data_points.extend(read_numbers(&path)?);
+            }
+            if data_points.is_empty() {
+                anyhow::bail!("No data points specified");
+            }
+            match weights {
+                Some(weights) => {
+                    let weights = weights.split(',')
+                        .map(|w| w.trim().parse::<f64>().map_err(|_| anyhow::anyhow!("Invalid weight `{}`", w)))
+                        .collect::<anyhow::Result<Vec<f64>>>()?;
+                    if weights.len() != data_points.len() {
+                        anyhow::bail!("{} weights specified for {} data points", weights.len(), data_points.len());
+                    }
+                    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+                        anyhow::bail!("Weights must be finite and not negative");
+                    }
+                    let index = rand::distributions::WeightedIndex::new(&weights)
+                        .map_err(|_| anyhow::anyhow!("At least one weight must be positive"))?;
+                    Box::new(WeightedValues { values: data_points, index })
+                }
+                None => Box::new(statrs::distribution::Empirical::from_vec(data_points)),
+            }
+        }
+        Distributions::Bootstrap(Bootstrap { from_file, block, mut data_points }) => {
+            if let Some(path) = from_file {
+                data_points.extend(read_numbers(&path)?);
+            }
+            if data_points.is_empty() {
+                anyhow::bail!("No data points specified");
+            }
+            if block < 1 || block > data_points.len() {
+                anyhow::bail!("--block must be from 1 to the number of data points {}, got {}", data_points.len(), block);
+            }
+            Box::new(BlockBootstrap::new(data_points, block))
+        }
+        Distributions::EmpiricalCdf(EmpiricalCdf { from_file }) => Box::new(PiecewiseLinearCdf::from_file(&from_file)?),
+        Distributions::Categorical(Categorical { from_file, labels, mut probabilities }) => {
+            if let Some(path) = from_file {
+                probabilities.extend(read_numbers(&path)?);
+            }
+            if let Some(labels) = labels {
+                let n = labels.split(',').count();
+                if n != probabilities.len() {
+                    anyhow::bail!("{} labels specified for {} probabilities", n, probabilities.len());
+                }
+            }
+            if let Some(p) = probabilities.iter().find(|p| p.is_nan() || **p < 0.0) {
+                anyhow::bail!("probabilities must not be negative, got {}", p);
+            }
+            Box::new(statrs::distribution::Categorical::new(&probabilities)?)
+        }
+        Distributions::Benford(Benford {}) => {
+            let probabilities : Vec<f64> = (1..=9).map(|d| (1.0 + 1.0 / d as f64).log10()).collect();
+            Box::new(BenfordDigits(statrs::distribution::Categorical::new(&probabilities)?))
+        }
+        Distributions::Beta(Beta { shape_a, shape_b }) => Box::new(statrs::distribution::Beta::new(shape_a,shape_b)?),
+        Distributions::BetaPrime(BetaPrime { alpha, beta }) => {
+            positive("alpha", alpha)?;
+            positive("beta", beta)?;
+            Box::new(BetaPrimeOdds(statrs::distribution::Beta::new(alpha, beta)?))
+        }
+        Distributions::Bernoulli(Bernoulli { probability }) => {
+            if !(0.0..=1.0).contains(&probability) {
+                anyhow::bail!("probability must be between 0 and 1, got {}", probability);
+            }
+            Box::new(statrs::distribution::Bernoulli::new(probability)?)
+        }
+        Distributions::Binomial(Binomial { probability, trials }) => {
+            if !(0.0..=1.0).contains(&probability) {
+                anyhow::bail!("probability must be between 0 and 1, got {}", probability);
+            }
+            if trials < 1 {
+                anyhow::bail!("number of trials must be at least 1");
+            }
+            Box::new(statrs::distribution::Binomial::new(probability,trials)?)
+        }
+        Distributions::NegativeBinomial(NegativeBinomial { successes, probability }) => {
+            positive("number of successes", successes)?;
+            if !(probability > 0.0 && probability <= 1.0) {
+                anyhow::bail!("probability must be above 0 and at most 1, got {}", probability);
+            }
+            Box::new(IntegerDistribution(statrs::distribution::NegativeBinomial::new(successes, probability)?))
+        }
+        Distributions::GammaPoisson(GammaPoisson { shape, rate }) => {
+            positive("shape", shape)?;
+            positive("rate", rate)?;
+            Box::new(GammaPoissonCompound { gamma: statrs::distribution::Gamma::new(shape, rate)? })
+        }
+        Distributions::DiscreteUniform(DiscreteUniform { min, max }) | Distributions::DiceUniform(DiceUniform { min, max }) => {
+            if max < min {
+                anyhow::bail!("max must not be less than min");
+            }
+            Box::new(statrs::distribution::DiscreteUniform::new(min,max)?)
+        }
+        Distributions::ChiSquared(ChiSquared { freedom }) => Box::new(statrs::distribution::ChiSquared::new(freedom)?),
+        Distributions::NoncentralChiSquared(NoncentralChiSquared { freedom, ncp }) => {
+            positive("freedom", freedom)?;
+            if !ncp.is_finite() || ncp < 0.0 {
+                anyhow::bail!("ncp must be finite and not negative, got {}", ncp);
+            }
+            Box::new(NoncentralChiSquaredPoisson::new(freedom, ncp)?)
+        }
+        Distributions::Chi(Chi { freedom }) => {
+            positive("freedom", freedom)?;
+            Box::new(statrs::distribution::Chi::new(freedom)?)
+        }
+        Distributions::Gamma(Gamma { shape, rate }) => {
+            positive("shape", shape)?;
+            positive("rate", rate)?;
+            Box::new(statrs::distribution::Gamma::new(shape,rate)?)
+        }
+        Distributions::Exp(Exp { rate }) => Box::new(statrs::distribution::Exp::new(rate)?),
+        Distributions::FisherSnedecor(FisherSnedecor { freedom_1, freedom_2 }) | Distributions::F(F { freedom_1, freedom_2 }) => {
+            positive("freedom_1", freedom_1)?;
+            positive("freedom_2", freedom_2)?;
+            Box::new(statrs::distribution::FisherSnedecor::new(freedom_1,freedom_2)?)
+        }
+        Distributions::Geometric(Geometric { probability }) => Box::new(statrs::distribution::Geometric::new(probability)?),
+        Distributions::Hypergeometric(Hypergeometric { population, successes, draws }) => Box::new(statrs::distribution::Hypergeometric::new(population,successes,draws)?),
+        Distributions::InverseGamma(InverseGamma { shape, rate }) | Distributions::InvGamma(InvGamma { shape, rate }) => {
+            positive("shape", shape)?;
+            positive("rate", rate)?;
+            Box::new(statrs::distribution::InverseGamma::new(shape,rate)?)
+        }
+        Distributions::Wald(Wald { mean, shape }) | Distributions::InverseGaussian(InverseGaussian { mean, shape }) => {
+            positive("mean", mean)?;
+            positive("shape", shape)?;
+            Box::new(WaldMichaelSchucanyHaas::new(mean, shape))
+        }
+        Distributions::Laplace(Laplace { location, scale }) => Box::new(LaplaceInverseCdf::new(location, scale)?),
+        Distributions::TukeyLambda(TukeyLambda { lambda }) => Box::new(TukeyLambdaInverseCdf::new(lambda)?),
+        Distributions::Pareto(Pareto { scale, shape }) => Box::new(ParetoInverseCdf::new(scale, shape)?),
+        Distributions::Poisson(Poisson { lambda }) => {
+            positive("lambda", lambda)?;
+            Box::new(statrs::distribution::Poisson::new(lambda)?)
+        }
+        Distributions::ZtPoisson(ZtPoisson { lambda }) => {
+            positive("lambda", lambda)?;
+            Box::new(ZtPoissonSampler::new(lambda)?)
+        }
+        Distributions::Weibull(Weibull { shape, scale }) => Box::new(statrs::distribution::Weibull::new(shape,scale)?),
+        Distributions::Gumbel(Gumbel { location, scale }) => Box::new(GumbelInverseCdf::new(location, scale)?),
+        Distributions::TruncatedNormal(TruncatedNormal { mean, std_dev, lo, hi }) => Box::new(TruncatedNormalInverseCdf::new(mean, std_dev, lo, hi)?),
+        Distributions::GenNormal(GenNormal { location, scale, shape }) => {
+            positive("scale", scale)?;
+            positive("shape", shape)?;
+            Box::new(GenNormalGamma::new(location, scale, shape)?)
+        }
+        Distributions::Rayleigh(Rayleigh { scale }) => Box::new(RayleighInverseCdf::new(scale)?),
+        Distributions::Maxwell(Maxwell { scale }) => {
+            positive("scale", scale)?;
+            Box::new(MaxwellChiSquared::new(scale)?)
+        }
+        Distributions::Planck(Planck { a }) => Box::new(PlanckZetaGamma::new(a)?),
+        Distributions::Frechet(Frechet { location, scale, shape }) => Box::new(FrechetInverseCdf::new(location, scale, shape)?),
+        Distributions::VonMises(VonMises { mu, kappa }) => {
+            if !kappa.is_finite() || kappa < 0.0 {
+                anyhow::bail!("kappa must be finite and not negative, got {}", kappa);
+            }
+            Box::new(VonMisesBestFisher::new(mu, kappa))
+        }
+        Distributions::Zipf(Zipf { n, exponent }) => {
+            if n < 1 {
+                anyhow::bail!("n must be at least 1");
+            }
+            if exponent < 0.0 {
+                anyhow::bail!("exponent must not be negative, got {}", exponent);
+            }
+            Box::new(rand_distr::Zipf::new(n, exponent)?)
+        }
+        Distributions::Zeta(Zeta { exponent }) => {
+            if exponent <= 1.0 {
+                anyhow::bail!("exponent must be greater than 1, got {}", exponent);
+            }
+            Box::new(rand_distr::Zeta::new(exponent)?)
+        }
+        Distributions::Mixture(Mixture { components }) => {
+            if components.is_empty() {
+                anyhow::bail!("At least one mixture component is required");
+            }
+            let mut weights = vec![];
+            let mut distributions = vec![];
+            for component in &components {
+                let (weight, distribution) = parse_mixture_component(component)?;
+                if weight < 0.0 {
+                    anyhow::bail!("Mixture weights must not be negative, got {}", weight);
+                }
+                weights.push(weight);
+                distributions.push(build_distribution(distribution)?);
+            }
+            Box::new(MixtureSampler {
+                components: distributions,
+                selector: statrs::distribution::Categorical::new(&weights)?,
+            })
+        }
+        Distributions::Interleave(Interleave { distributions }) => {
+            if distributions.len() < 2 {
+                anyhow::bail!("At least two distributions are required to interleave");
+            }
+            let distributions = distributions.iter()
+                .map(|spec| build_distribution(parse_spec(spec)?))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Box::new(InterleavedSampler { distributions, next: std::cell::Cell::new(0) })
+        }
+        Distributions::Constant(Constant { value }) => Box::new(statrs::distribution::Dirac::new(value)?),
+        Distributions::Dirichlet(_) | Distributions::Multinomial(_) |
+        Distributions::UnitCircle(_) | Distributions::Gaussian2d(_) |
+        Distributions::UnitDisc(_) | Distributions::UnitSphere(_) | Distributions::UnitBall(_) => anyhow::bail!("Vector-valued distribution cannot be used here"),
+    };
+    Ok(d)
+}
+
+/// Build inverse CDF of the specified distribution. Supported distributions are
+/// uniform, normal, lognormal, logitnormal, truncatednormal, cauchy, triangular, exp, laplace, tukeylambda, empiricalcdf, pareto, weibull, gumbel, rayleigh and frechet
+pub fn build_quantile_function(distribution: Distributions) -> anyhow::Result<Box<dyn QuantileFunction>> {
+    use statrs::distribution::ContinuousCDF;
+    let q: Box<dyn QuantileFunction> = match distribution {
+        Distributions::Uniform(Uniform { right_inclusive: _, min, max }) => {
+            if max <= min {
+                anyhow::bail!("Invalid distribution parameters");
+            }
+            Box::new(move |p: f64| min + p * (max - min))
+        }
+        Distributions::Normal(Normal { mean, std_dev }) => {
+            let d = statrs::distribution::Normal::new(mean, std_dev)?;
+            Box::new(move |p: f64| d.inverse_cdf(p))
+        }
+        Distributions::LogNormal(LogNormal { location, scale }) => Box::new(Exponentiated(underlying_normal(location, scale)?)),
+        Distributions::LogitNormal(LogitNormal { location, scale }) => Box::new(Sigmoid(underlying_normal(location, scale)?)),
+        Distributions::TruncatedNormal(TruncatedNormal { mean, std_dev, lo, hi }) => Box::new(TruncatedNormalInverseCdf::new(mean, std_dev, lo, hi)?),
+        Distributions::Cauchy(Cauchy { location, scale }) => {
+            statrs::distribution::Cauchy::new(location, scale)?;
+            Box::new(move |p: f64| location + scale * (PI * (p - 0.5)).tan())
+        }
+        Distributions::Triangular(Triangular { min, mode, max }) => {
+            if !(min <= mode && mode <= max && min < max) {
+                anyhow::bail!("Expected min <= mode <= max and min < max");
+            }
+            let p_mode = (mode - min) / (max - min);
+            Box::new(move |p: f64| if p < p_mode {
+                min + (p * (max - min) * (mode - min)).sqrt()
+            } else {
+                max - ((1.0 - p) * (max - min) * (max - mode)).sqrt()
+            })
+        }
+        Distributions::Exp(Exp { rate }) => {
+            statrs::distribution::Exp::new(rate)?;
+            Box::new(move |p: f64| -(-p).ln_1p() / rate)
+        }
+        Distributions::Laplace(Laplace { location, scale }) => Box::new(LaplaceInverseCdf::new(location, scale)?),
+        Distributions::TukeyLambda(TukeyLambda { lambda }) => Box::new(TukeyLambdaInverseCdf::new(lambda)?),
+        Distributions::EmpiricalCdf(EmpiricalCdf { from_file }) => Box::new(PiecewiseLinearCdf::from_file(&from_file)?),
+        Distributions::Pareto(Pareto { scale, shape }) => Box::new(ParetoInverseCdf::new(scale, shape)?),
+        Distributions::Weibull(Weibull { shape, scale }) => {
+            statrs::distribution::Weibull::new(shape, scale)?;
+            Box::new(move |p: f64| scale * (-(-p).ln_1p()).powf(1.0 / shape))
+        }
+        Distributions::Gumbel(Gumbel { location, scale }) => Box::new(GumbelInverseCdf::new(location, scale)?),
+        Distributions::Rayleigh(Rayleigh { scale }) => Box::new(RayleighInverseCdf::new(scale)?),
+        Distributions::Frechet(Frechet { location, scale, shape }) => Box::new(FrechetInverseCdf::new(location, scale, shape)?),
+        _ => anyhow::bail!("Inverse CDF is not available for this distribution"),
+    };
+    Ok(q)
+}
+
+/// Zero-inflation of a distribution, typically a discrete one - generates 0 with probability `p`,
+/// otherwise a sample of the base distribution
+pub struct ZeroInflated {
+    base: Box<dyn DistributionObject>,
+    p: f64,
+}
+
+impl ZeroInflated {
+    pub fn new(base: Box<dyn DistributionObject>, p: f64) -> Self {
+        Self { base, p }
+    }
+}
+
+impl DistributionObject for ZeroInflated {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        if rand::Rng::gen::<f64>(rng) < self.p {
+            0.0
+        } else {
+            self.base.sample(rng)
+        }
+    }
+
+    fn reset(&self) {
+        self.base.reset();
+    }
+}
+
+/// Antithetic variates - draws uniform numbers `u` and outputs `F^-1(u)` and `F^-1(1-u)` as two consecutive samples
+pub struct Antithetic {
+    quantile: Box<dyn QuantileFunction>,
+    pending: std::cell::Cell<Option<f64>>,
+    u_dist: rand::distributions::Uniform<f64>,
+}
+
+impl Antithetic {
+    pub fn new(quantile: Box<dyn QuantileFunction>) -> Self {
+        Self {
+            quantile,
+            pending: std::cell::Cell::new(None),
+            u_dist: rand::distributions::Uniform::new(f64::EPSILON, 1.0),
+        }
+    }
+}
+
+impl DistributionObject for Antithetic {
+    fn sample(&self, rng: &mut dyn rand::RngCore) -> f64 {
+        if let Some(x) = self.pending.take() {
+            return x;
+        }
+        let u = self.u_dist.sample(rng);
+        self.pending.set(Some(self.quantile.inverse_cdf(1.0 - u)));
+        self.quantile.inverse_cdf(u)
+    }
+
+    fn reset(&self) {
+        self.pending.set(None);
+    }
+}
+
+/// Quasi-random sampling - feeds points of the Halton low-discrepancy sequence to the quantile function instead of
+/// pseudorandom numbers, so that averages converge faster in numerical integration.
+/// Each of `dimensions` consecutive samples is a coordinate of the same point, using consecutive prime bases.
+/// The random number generator is not used, so the output is always the same.
+pub struct Halton {
+    quantile: Box<dyn QuantileFunction>,
+    bases: Vec<u64>,
+    index: std::cell::Cell<u64>,
+    dimension: std::cell::Cell<usize>,
+}
+
+impl Halton {
+    pub fn new(quantile: Box<dyn QuantileFunction>, dimensions: usize) -> Self {
+        let mut bases = vec![];
+        let mut candidate = 2;
+        while bases.len() < dimensions {
+            if bases.iter().all(|b| candidate % b != 0) {
+                bases.push(candidate);
+            }
+            candidate += 1;
+        }
+        Self {
+            quantile,
+            bases,
+            // Skip the initial point which is 0 in all dimensions
+            index: std::cell::Cell::new(1),
+            dimension: std::cell::Cell::new(0),
+        }
+    }
+}
+
+/// Van der Corput sequence element: digits of `index` in the specified base, mirrored about the radix point
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let mut result = 0.0;
+    let mut f = 1.0 / base as f64;
+    while index > 0 {
+        result += f * (index % base) as f64;
+        index /= base;
+        f /= base as f64;
+    }
+    result
+}
+
+impl DistributionObject for Halton {
+    fn sample(&self, _rng: &mut dyn rand::RngCore) -> f64 {
+        let dimension = self.dimension.get();
+        let u = radical_inverse(self.index.get(), self.bases[dimension]);
+        if dimension + 1 == self.bases.len() {
+            self.dimension.set(0);
+            self.index.set(self.index.get() + 1);
+        } else {
+            self.dimension.set(dimension + 1);
+        }
+        self.quantile.inverse_cdf(u)
+    }
+}
+
+/// Build either a scalar or a vector-valued sampler for the specified distribution
+pub fn build_sampler(distribution: Distributions) -> anyhow::Result<Sampler> {
+    Ok(match distribution {
+        Distributions::Dirichlet(Dirichlet { concentrations }) => {
+            if concentrations.len() < 2 {
+                anyhow::bail!("At least two concentration parameters are required");
+            }
+            for &a in &concentrations {
+                positive("concentrations", a)?;
+            }
+            Sampler::Vector(Box::new(DirichletGamma::new(&concentrations)?))
+        }
+        Distributions::Multinomial(Multinomial { trials, probabilities }) => {
+            if trials < 1 {
+                anyhow::bail!("number of trials must be at least 1");
+            }
+            if let Some(p) = probabilities.iter().find(|&&p| p < 0.0) {
+                anyhow::bail!("probabilities must not be negative, got {}", p);
+            }
+            Sampler::Vector(Box::new(statrs::distribution::Multinomial::new(&probabilities, trials)?))
+        }
+        Distributions::UnitCircle(UnitCircle {}) => Sampler::Vector(Box::new(ArrayDistribution::<_, 2>(rand_distr::UnitCircle))),
+        Distributions::UnitDisc(UnitDisc {}) => Sampler::Vector(Box::new(ArrayDistribution::<_, 2>(rand_distr::UnitDisc))),
+        Distributions::UnitSphere(UnitSphere {}) => Sampler::Vector(Box::new(ArrayDistribution::<_, 3>(rand_distr::UnitSphere))),
+        Distributions::UnitBall(UnitBall {}) => Sampler::Vector(Box::new(ArrayDistribution::<_, 3>(rand_distr::UnitBall))),
+        Distributions::Gaussian2d(Gaussian2d { std_dev }) => {
+            positive("std_dev", std_dev)?;
+            Sampler::Vector(Box::new(Gaussian2dPolar { radius: RayleighInverseCdf::new(std_dev)? }))
+        }
+        distribution => Sampler::Scalar(build_distribution(distribution)?),
+    })
+}
+
+/// What to do with values outside of [`Transform`]'s limits
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRange {
+    /// Drop the whole sample and draw another one
+    Discard,
+    /// Replace the value with the nearest limit
+    Clamp,
+    /// Leave the value as is
+    Keep,
+}
+
+/// Transformations applied to each value of a sample, in the order of the fields
+#[derive(Clone)]
+pub struct Transform {
+    pub abs: bool,
+    pub exponentiate: bool,
+    /// base of the exponentiation, e if not specified
+    pub exp_base: Option<f64>,
+    pub scale: f64,
+    pub offset: f64,
+    pub round: Option<f64>,
+    /// Euclidean remainder of division by the specified positive number, from 0 to it (exclusive)
+    pub modulo: Option<f64>,
+    pub below: Option<f64>,
+    pub above: Option<f64>,
+    pub out_of_range: OutOfRange,
+    /// discard NaN and infinite values
+    pub drop_nonfinite: bool,
+    /// randomly discard values, keeping each with probability exp(-temper*|x|), which tempers heavy tails.
+    /// Needs random numbers, so it is applied by [`SampleStream`] instead of [`Transform::apply`]
+    pub temper: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            abs: false,
+            exponentiate: false,
+            exp_base: None,
+            scale: 1.0,
+            offset: 0.0,
+            round: None,
+            modulo: None,
+            below: None,
+            above: None,
+            out_of_range: OutOfRange::Discard,
+            drop_nonfinite: false,
+            temper: 0.0,
+        }
+    }
+}
+
+impl Transform {
+    /// Returns transformed value or `None` if the sample should be discarded
+    pub fn apply(&self, mut x: f64) -> Option<f64> {
+        if self.abs { x = x.abs(); }
+        if self.exponentiate {
+            x = match self.exp_base {
+                Some(b) => b.powf(x),
+                None => x.exp(),
+            };
+        }
+        if self.scale != 1.0 || self.offset != 0.0 { x = x * self.scale + self.offset; }
+        if let Some(step) = self.round { x = (x / step).round() * step; }
+        if let Some(m) = self.modulo {
+            x = x.rem_euclid(m);
+            // Tiny negative values can round up to m
+            if x == m { x = 0.0; }
+        }
+        match self.out_of_range {
+            OutOfRange::Discard => {
+                if self.below.is_some_and(|limit| x < limit) || self.above.is_some_and(|limit| x > limit) {
+                    return None;
+                }
+            }
+            OutOfRange::Clamp => {
+                if let Some(limit) = self.below {
+                    if x < limit { x = limit; }
+                }
+                if let Some(limit) = self.above {
+                    if x > limit { x = limit; }
+                }
+            }
+            OutOfRange::Keep => (),
+        }
+        if self.drop_nonfinite && !x.is_finite() {
+            return None;
+        }
+        Some(x)
+    }
+}
+
+/// [`SampleStream`] gives up if this many samples were discarded without any being accepted
+pub const MAX_DISCARDS_BEFORE_FIRST_SAMPLE : u64 = 1_000_000;
+
+/// Endless stream of transformed values of a distribution.
+///
+/// Values go in rows of [`SampleStream::row_len`] - either multiple independent samples
+/// of a scalar distribution (see [`SampleStream::columns`]) or one sample of a vector-valued distribution.
+/// A row is discarded as a whole if any of its values is discarded by the [`Transform`].
+pub struct SampleStream<R> {
+    sampler: Sampler,
+    rng: R,
+    transform: Transform,
+    columns: usize,
+    row: Vec<f64>,
+    pos: usize,
+    accepted: u64,
+    discarded: u64,
+    gave_up: bool,
+}
+
+impl<R: rand::RngCore> SampleStream<R> {
+    pub fn new(sampler: Sampler, rng: R, transform: Transform) -> Self {
+        Self {
+            sampler,
+            rng,
+            transform,
+            columns: 1,
+            row: vec![],
+            pos: 0,
+            accepted: 0,
+            discarded: 0,
+            gave_up: false,
+        }
+    }
+
+    /// Draw specified number of independent samples of a scalar distribution per row
+    pub fn columns(mut self, columns: usize) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Replace the sampler, e.g. with the same distribution using other parameters.
+    /// New sampler must produce rows of the same length.
+    pub fn set_sampler(&mut self, sampler: Sampler) -> anyhow::Result<()> {
+        let old_len = self.row_len();
+        let old = std::mem::replace(&mut self.sampler, sampler);
+        if self.row_len() != old_len {
+            self.sampler = old;
+            anyhow::bail!("Number of values per row must not change");
+        }
+        Ok(())
+    }
+
+    /// Continue with another PRNG as if the stream was new: the state of the sampler
+    /// and the rest of the current row are dropped, counters of samples are kept
+    pub fn restart(&mut self, rng: R) {
+        self.rng = rng;
+        self.sampler.reset();
+        self.row.clear();
+        self.pos = 0;
+    }
+
+    /// Number of values in each row
+    pub fn row_len(&self) -> usize {
+        match &self.sampler {
+            Sampler::Scalar(_) => self.columns,
+            Sampler::Vector(d) => d.dimensions(),
+        }
+    }
+
+    /// Number of rows accepted so far, including any that the caller does not output
+    pub fn accepted(&self) -> u64 {
+        self.accepted
+    }
+
+    /// Number of rows discarded so far
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+
+    /// Whether the stream ended because of [`MAX_DISCARDS_BEFORE_FIRST_SAMPLE`]
+    pub fn gave_up(&self) -> bool {
+        self.gave_up
+    }
+
+    fn fill_row(&mut self) -> bool {
+        loop {
+            match &self.sampler {
+                Sampler::Scalar(d) => {
+                    self.row.clear();
+                    for _ in 0..self.columns {
+                        self.row.push(d.sample(&mut self.rng));
+                    }
+                }
+                Sampler::Vector(d) => self.row = d.sample(&mut self.rng),
+            }
+            let mut discard = false;
+            for x in self.row.iter_mut() {
+                match self.transform.apply(*x) {
+                    Some(y) => *x = y,
+                    None => discard = true,
+                }
+            }
+            if !discard && self.transform.temper > 0.0 {
+                for x in self.row.iter() {
+                    if rand::Rng::gen::<f64>(&mut self.rng) >= (-self.transform.temper * x.abs()).exp() {
+                        discard = true;
+                    }
+                }
+            }
+            if !discard {
+                self.accepted += 1;
+                return true;
+            }
+            self.discarded += 1;
+            if self.accepted == 0 && self.discarded >= MAX_DISCARDS_BEFORE_FIRST_SAMPLE {
+                self.gave_up = true;
+                return false;
+            }
+        }
+    }
+}
+
+impl<R: rand::RngCore> Iterator for SampleStream<R> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if self.pos >= self.row.len() {
+            if self.gave_up || !self.fill_row() {
+                return None;
+            }
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(self.row[self.pos - 1])
+    }
+}
+
+/// Philox4x32-10 counter-based PRNG of Salmon et al. (2011). Each block of 4 output words is a keyed bijection
+/// of a 128-bit block counter, so the stream is the same on all platforms and any position of it can be computed
+/// directly with [`Philox4x32::seek`]. The key is the seed itself
+pub struct Philox4x32 {
+    key: [u32; 2],
+    counter: u128,
+    block: [u32; 4],
+    index: usize,
+}
+
+impl Philox4x32 {
+    const M0 : u32 = 0xD2511F53;
+    const M1 : u32 = 0xCD9E8D57;
+    const W0 : u32 = 0x9E3779B9;
+    const W1 : u32 = 0xBB67AE85;
+
+    pub fn new(key: u64) -> Self {
+        Self { key: [key as u32, (key >> 32) as u32], counter: 0, block: [0; 4], index: 4 }
+    }
+
+    /// Output words for the specified block counter
+    pub fn block(&self, counter: u128) -> [u32; 4] {
+        let mut c = [counter as u32, (counter >> 32) as u32, (counter >> 64) as u32, (counter >> 96) as u32];
+        let mut k = self.key;
+        for round in 0..10 {
+            if round > 0 {
+                k = [k[0].wrapping_add(Self::W0), k[1].wrapping_add(Self::W1)];
+            }
+            let p0 = Self::M0 as u64 * c[0] as u64;
+            let p1 = Self::M1 as u64 * c[2] as u64;
+            c = [(p1 >> 32) as u32 ^ c[1] ^ k[0], p1 as u32, (p0 >> 32) as u32 ^ c[3] ^ k[1], p0 as u32];
+        }
+        c
+    }
+
+    /// Continue the stream from the specified 32-bit word, counting from 0
+    pub fn seek(&mut self, word: u128) {
+        self.counter = word / 4;
+        self.index = 4;
+        if !word.is_multiple_of(4) {
+            self.block = self.block(self.counter);
+            self.counter = self.counter.wrapping_add(1);
+            self.index = (word % 4) as usize;
+        }
+    }
+}
+
+impl rand::RngCore for Philox4x32 {
+    fn next_u32(&mut self) -> u32 {
+        if self.index == 4 {
+            self.block = self.block(self.counter);
+            self.counter = self.counter.wrapping_add(1);
+            self.index = 0;
+        }
+        self.index += 1;
+        self.block[self.index - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let lo = self.next_u32() as u64;
+        let hi = self.next_u32() as u64;
+        hi << 32 | lo
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let word = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl rand::SeedableRng for Philox4x32 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> Self {
+        Self::new(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        Self::new(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn sample_mean(d: &dyn DistributionObject, n: usize) -> f64 {
+        let mut r = rand::rngs::SmallRng::seed_from_u64(1);
+        (0..n).map(|_| d.sample(&mut r)).sum::<f64>() / n as f64
+    }
+
+    fn samples(d: &dyn DistributionObject, n: usize) -> Vec<f64> {
+        let mut r = rand::rngs::SmallRng::seed_from_u64(1);
+        (0..n).map(|_| d.sample(&mut r)).collect()
+    }
+
+    #[test]
+    fn stream_discards_whole_rows() {
+        let d = build_sampler(Distributions::Uniform(Uniform { right_inclusive: false, min: 0.0, max: 1.0 })).unwrap();
+        let transform = Transform { below: Some(0.5), ..Transform::default() };
+        let rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut stream = SampleStream::new(d, rng, transform).columns(2);
+        assert_eq!(stream.row_len(), 2);
+        assert!(stream.by_ref().take(1000).all(|x| x >= 0.5));
+        // each row of two is accepted with probability 1/4
+        assert!(stream.discarded() > 1000);
+    }
+
+    #[test]
+    fn parse_spec_with_negative_numbers() {
+        assert!(matches!(parse_spec("uniform:-2:-1").unwrap(), Distributions::Uniform(Uniform { min, max, .. }) if min == -2.0 && max == -1.0));
+        assert!(matches!(parse_spec("uniform:--right-inclusive:0:1").unwrap(), Distributions::Uniform(Uniform { right_inclusive: true, .. })));
+        assert!(parse_spec("nosuchdistribution:1").is_err());
+        assert!(parse_spec("normal:0").is_err());
+    }
+
+    #[test]
+    fn antithetic_pairs() {
+        let q = build_quantile_function(Distributions::Normal(Normal { mean: 1.0, std_dev: 2.0 })).unwrap();
+        assert!((q.inverse_cdf(0.5) - 1.0).abs() < 1e-12);
+        assert!((q.inverse_cdf(0.975) - (1.0 + 2.0 * 1.959963984540054)).abs() < 1e-6);
+        let d = Antithetic::new(q);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let (a, b) = (d.sample(&mut rng), d.sample(&mut rng));
+            assert!((a + b - 2.0).abs() < 1e-9, "{} {}", a, b);
+        }
+        assert!(build_quantile_function(Distributions::Poisson(Poisson { lambda: 1.0 })).is_err());
+    }
+
+    #[test]
+    fn halton_sequence() {
+        let q = build_quantile_function(Distributions::Uniform(Uniform { right_inclusive: false, min: 0.0, max: 1.0 })).unwrap();
+        let d = Halton::new(q, 2);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let v : Vec<f64> = (0..6).map(|_| d.sample(&mut rng)).collect();
+        assert_eq!(v, [0.5, 1.0/3.0, 0.25, 2.0/3.0, 0.75, 1.0/9.0]);
+    }
+
+    #[test]
+    fn modulo_wraps_negative_values() {
+        let transform = Transform { modulo: Some(3.0), ..Transform::default() };
+        assert_eq!(transform.apply(7.0), Some(1.0));
+        assert_eq!(transform.apply(-1.0), Some(2.0));
+        assert_eq!(transform.apply(-1e-20), Some(0.0));
+        assert_eq!(transform.apply(-0.5), Some(2.5));
+    }
+
+    #[test]
+    fn tempering_keeps_small_values() {
+        // exp(-1*|x|) is almost 1 near 0 and almost 0 far from 0
+        let d = build_sampler(Distributions::Uniform(Uniform { right_inclusive: false, min: -10.0, max: 10.0 })).unwrap();
+        let transform = Transform { temper: 1.0, ..Transform::default() };
+        let rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let v : Vec<f64> = SampleStream::new(d, rng, transform).take(10000).collect();
+        let small = v.iter().filter(|x| x.abs() < 1.0).count();
+        // density of kept values is proportional to exp(-|x|), so P(|x| < 1) = 1 - e^-1 (slightly more due to the cut at 10)
+        assert!((small as f64 / 10000.0 - 0.632).abs() < 0.02, "{}", small);
+    }
+
+    #[test]
+    fn mixture_latent_is_component_index() {
+        let d = build_sampler(parse_args("mixture", &["0.5:constant:1", "0.5:constant:2"]).unwrap()).unwrap().with_latent().unwrap();
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for _ in 0..100 {
+            match d {
+                Sampler::Vector(ref d) => {
+                    let v = d.sample(&mut rng);
+                    assert_eq!(v[0], v[1] + 1.0);
+                }
+                Sampler::Scalar(_) => panic!("expected vector sampler"),
+            }
+        }
+        assert!(build_sampler(parse_spec("normal:0:1").unwrap()).unwrap().with_latent().is_err());
+    }
+
+    #[test]
+    fn philox_known_answers() {
+        // Test vectors of the Random123 library
+        assert_eq!(Philox4x32::new(0).block(0), [0x6627e8d5, 0xe169c58d, 0xbc57ac4c, 0x9b00dbd8]);
+        assert_eq!(Philox4x32::new(u64::MAX).block(u128::MAX), [0x408f276d, 0x41c83b0e, 0xa20bc7c6, 0x6d5451fd]);
+        assert_eq!(Philox4x32::new(0x299f31d0_a4093822).block(0x03707344_13198a2e_85a308d3_243f6a88),
+            [0xd16cfe09, 0x94fdcceb, 0x5001e420, 0x24126ea1]);
+
+        let mut rng = Philox4x32::new(42);
+        let words : Vec<u32> = (0..11).map(|_| rand::RngCore::next_u32(&mut rng)).collect();
+        for start in [0, 3, 4, 9] {
+            rng.seek(start as u128);
+            assert_eq!(rand::RngCore::next_u32(&mut rng), words[start]);
+            assert_eq!(rand::RngCore::next_u32(&mut rng), words[start + 1]);
+        }
+    }
+
+    #[test]
+    fn planck_mean() {
+        let d = build_distribution(Distributions::Planck(Planck { a: 3.0 })).unwrap();
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        assert!((0..1000).all(|_| d.sample(&mut rng) > 0.0));
+        // (a+1) * zeta(a+2) / zeta(a+1)
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 4.0 * 1.036_927_755_143_37 / 1.082_323_233_711_138).abs() < 0.03, "mean {}", mean);
+
+        assert!(build_distribution(Distributions::Planck(Planck { a: 1e-20 })).is_err());
+    }
+
+    #[test]
+    fn triangular_argument_order() {
+        let d = build_distribution(Distributions::Triangular(Triangular { min: 0.0, mode: 1.0, max: 10.0 })).unwrap();
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 11.0/3.0).abs() < 0.05, "mean {}", mean);
+
+        assert!(build_distribution(Distributions::Triangular(Triangular { min: 0.0, mode: 10.0, max: 1.0 })).is_err());
+    }
+
+    #[test]
+    fn vonmises_mean_resultant_length() {
+        let d = build_distribution(Distributions::VonMises(VonMises { mu: 0.0, kappa: 2.0 })).unwrap();
+        let v = samples(&*d, 100000);
+        assert!(v.iter().all(|x| (-PI..=PI).contains(x)));
+        // E[cos x] = I1(kappa) / I0(kappa)
+        let mean_cos = v.iter().map(|x| x.cos()).sum::<f64>() / v.len() as f64;
+        assert!((mean_cos - 1.590_636_854_637_329 / 2.279_585_302_336_067).abs() < 0.01, "mean cos {}", mean_cos);
+        let mean_sin = v.iter().map(|x| x.sin()).sum::<f64>() / v.len() as f64;
+        assert!(mean_sin.abs() < 0.01, "mean sin {}", mean_sin);
+
+        // normal approximation with variance 1/kappa
+        let d = build_distribution(Distributions::VonMises(VonMises { mu: 1.0, kappa: 1e8 })).unwrap();
+        assert!((sample_mean(&*d, 10000) - 1.0).abs() < 1e-5);
+        assert!(build_distribution(Distributions::VonMises(VonMises { mu: 0.0, kappa: f64::INFINITY })).is_err());
+    }
+
+    #[test]
+    fn truncatednormal_mean() {
+        let d = build_distribution(Distributions::TruncatedNormal(TruncatedNormal { mean: 0.0, std_dev: 1.0, lo: -1.0, hi: 2.0 })).unwrap();
+        assert!(samples(&*d, 10000).iter().all(|x| (-1.0..=2.0).contains(x)));
+        // (phi(-1) - phi(2)) / (Phi(2) - Phi(-1))
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 0.229_637).abs() < 0.01, "mean {}", mean);
+
+        // far in the upper tail, mirrored to the lower one
+        let d = build_distribution(Distributions::TruncatedNormal(TruncatedNormal { mean: 0.0, std_dev: 1.0, lo: 8.0, hi: 9.0 })).unwrap();
+        assert!(samples(&*d, 10000).iter().all(|x| (8.0..=9.0).contains(x)));
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 8.121).abs() < 0.01, "mean {}", mean);
+    }
+
+    #[test]
+    fn gennormal_mean_absolute_deviation() {
+        for (shape, expected) in [(1.0, 2.0), (2.0, 2.0 / PI.sqrt())] {
+            let d = build_distribution(Distributions::GenNormal(GenNormal { location: 1.0, scale: 2.0, shape })).unwrap();
+            let v = samples(&*d, 100000);
+            let mean = v.iter().sum::<f64>() / v.len() as f64;
+            assert!((mean - 1.0).abs() < 0.03, "shape {} mean {}", shape, mean);
+            // E|x - location| = scale * Gamma(2/shape) / Gamma(1/shape)
+            let deviation = v.iter().map(|x| (x - 1.0).abs()).sum::<f64>() / v.len() as f64;
+            assert!((deviation - expected).abs() < 0.03, "shape {} deviation {}", shape, deviation);
+        }
+    }
+
+    #[test]
+    fn wald_mean_and_variance() {
+        let d = build_distribution(Distributions::Wald(Wald { mean: 2.0, shape: 3.0 })).unwrap();
+        let v = samples(&*d, 100000);
+        assert!(v.iter().all(|&x| x > 0.0));
+        let mean = v.iter().sum::<f64>() / v.len() as f64;
+        assert!((mean - 2.0).abs() < 0.03, "mean {}", mean);
+        // mean^3 / shape
+        let variance = v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / v.len() as f64;
+        assert!((variance - 8.0 / 3.0).abs() < 0.15, "variance {}", variance);
+    }
+
+    #[test]
+    fn tukeylambda_support_and_variance() {
+        let d = build_distribution(Distributions::TukeyLambda(TukeyLambda { lambda: 0.5 })).unwrap();
+        let v = samples(&*d, 100000);
+        // support is from -1/lambda to 1/lambda
+        assert!(v.iter().all(|x| (-2.0..=2.0).contains(x)));
+        let mean = v.iter().sum::<f64>() / v.len() as f64;
+        assert!(mean.abs() < 0.01, "mean {}", mean);
+        // 2/lambda^2 * (1/(1+2*lambda) - Gamma(lambda+1)^2/Gamma(2*lambda+2))
+        let variance = v.iter().map(|x| x * x).sum::<f64>() / v.len() as f64;
+        assert!((variance - 8.0 * (0.5 - PI / 8.0)).abs() < 0.02, "variance {}", variance);
+
+        // logistic distribution for lambda 0
+        let q = build_quantile_function(Distributions::TukeyLambda(TukeyLambda { lambda: 0.0 })).unwrap();
+        assert!((q.inverse_cdf(0.75) - 3.0f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn noncentralt_mean() {
+        let d = build_distribution(Distributions::NoncentralT(NoncentralT { freedom: 10.0, ncp: 1.0 })).unwrap();
+        // ncp * sqrt(freedom/2) * Gamma((freedom-1)/2) / Gamma(freedom/2)
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 5.0f64.sqrt() * 11.631_728_396_567_45 / 24.0).abs() < 0.02, "mean {}", mean);
+    }
+
+    #[test]
+    fn noncentralchi2_mean() {
+        for (freedom, ncp) in [(3.0, 2.0), (0.5, 0.0)] {
+            let d = build_distribution(Distributions::NoncentralChiSquared(NoncentralChiSquared { freedom, ncp })).unwrap();
+            assert!(samples(&*d, 10000).iter().all(|&x| x >= 0.0));
+            let mean = sample_mean(&*d, 100000);
+            assert!((mean - (freedom + ncp)).abs() < 0.05, "freedom {} ncp {} mean {}", freedom, ncp, mean);
+        }
+    }
+
+    #[test]
+    fn bootstrap_blocks() {
+        let d = build_distribution(Distributions::Bootstrap(Bootstrap { from_file: None, block: 2, data_points: vec![1.0, 2.0, 3.0, 4.0, 5.0] })).unwrap();
+        let v = samples(&*d, 100000);
+        for pair in v.chunks(2) {
+            assert!((1.0..=4.0).contains(&pair[0]) && pair[1] == pair[0] + 1.0, "{:?}", pair);
+        }
+        // blocks start uniformly at 1 to 4
+        let mean = v.iter().sum::<f64>() / v.len() as f64;
+        assert!((mean - 3.0).abs() < 0.02, "mean {}", mean);
+    }
+
+    #[test]
+    fn ztpoisson_mean() {
+        // both inversion for small lambda and rejection for large one
+        for lambda in [0.5, 3.0] {
+            let d = build_distribution(Distributions::ZtPoisson(ZtPoisson { lambda })).unwrap();
+            assert!(samples(&*d, 10000).iter().all(|&x| x >= 1.0 && x.fract() == 0.0));
+            let mean = sample_mean(&*d, 100000);
+            assert!((mean - lambda / (1.0 - (-lambda).exp())).abs() < 0.02, "lambda {} mean {}", lambda, mean);
+        }
+    }
+
+    #[test]
+    fn empiricalcdf_from_file() {
+        let path = std::env::temp_dir().join(format!("empiricalcdf-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "0 0\n1 0.5\n3 1\n").unwrap();
+        let d = build_distribution(Distributions::EmpiricalCdf(EmpiricalCdf { from_file: path.clone() })).unwrap();
+        let q = build_quantile_function(Distributions::EmpiricalCdf(EmpiricalCdf { from_file: path.clone() })).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(samples(&*d, 10000).iter().all(|x| (0.0..=3.0).contains(x)));
+        // uniform on [0, 1] and on [1, 3] with probability 1/2 each
+        let mean = sample_mean(&*d, 100000);
+        assert!((mean - 1.25).abs() < 0.02, "mean {}", mean);
+        assert_eq!(q.inverse_cdf(0.25), 0.5);
+        assert_eq!(q.inverse_cdf(0.75), 2.0);
+    }
+}
@@ -1,11 +1,12 @@
+use anyhow::Context;
 use rand::SeedableRng;
+use std::convert::TryFrom;
 use std::io::Write;
-use std::f64::consts::FRAC_PI_2;
-use std::f64::consts::FRAC_2_PI;
 use byteorder::{BE,LE};
 use byteorder::WriteBytesExt;
+use random_distributions_cli::{build_quantile_function, build_sampler, parse_args, Antithetic, Distributions, Halton, OutOfRange, Philox4x32, SampleStream, Sampler, Transform, ZeroInflated};
 
-#[derive(strum_macros::EnumString)]
+#[derive(strum_macros::EnumString, Clone, Copy)]
 #[strum(ascii_case_insensitive)]
 enum BinaryFormat {
     F32BE,
@@ -28,35 +29,243 @@ enum BinaryFormat {
     S64LE,
 }
 
+impl BinaryFormat {
+    /// Smallest and largest values of integer formats
+    fn integer_range(&self) -> Option<(f64, f64)> {
+        Some(match self {
+            BinaryFormat::F32BE | BinaryFormat::F32LE | BinaryFormat::F64BE | BinaryFormat::F64LE => return None,
+            BinaryFormat::U8 => (0.0, u8::MAX as f64),
+            BinaryFormat::U16BE | BinaryFormat::U16LE => (0.0, u16::MAX as f64),
+            BinaryFormat::U32BE | BinaryFormat::U32LE => (0.0, u32::MAX as f64),
+            BinaryFormat::U64BE | BinaryFormat::U64LE => (0.0, u64::MAX as f64),
+            BinaryFormat::S8 => (i8::MIN as f64, i8::MAX as f64),
+            BinaryFormat::S16BE | BinaryFormat::S16LE => (i16::MIN as f64, i16::MAX as f64),
+            BinaryFormat::S32BE | BinaryFormat::S32LE => (i32::MIN as f64, i32::MAX as f64),
+            BinaryFormat::S64BE | BinaryFormat::S64LE => (i64::MIN as f64, i64::MAX as f64),
+        })
+    }
+}
+
+#[derive(strum_macros::EnumString, Clone, Copy)]
+#[strum(ascii_case_insensitive)]
+enum Rounding {
+    Trunc,
+    Nearest,
+    Stochastic,
+}
+
+#[derive(strum_macros::EnumString, Clone, Copy)]
+#[strum(ascii_case_insensitive)]
+enum RngAlgorithm {
+    Small,
+    ChaCha8,
+    ChaCha12,
+    ChaCha20,
+    Pcg64,
+    Philox,
+}
+
 /// Command-line tool to generate samples of various random distributions.
 #[derive(argh::FromArgs)]
 struct Opts {
-    /// number of digits after decimal to print
-    #[argh(option,default="10",short='p')]
-    precision: usize,
+    /// number of digits after decimal to print. Defaults to 10, or to 0 for integer-valued output, e.g. for poisson or binomial distributions or with --round 1.
+    /// With 0, values are rounded to integers and printed without decimal point
+    #[argh(option,short='p')]
+    precision: Option<usize>,
+
+    /// print values rounded to the specified number of significant digits instead of fixed number of digits after decimal
+    #[argh(option)]
+    significant: Option<usize>,
 
     /// add value of each sample to accumulator, outputting a random walk instead of individual samples
     #[argh(switch,short='C')]
     cumulative: bool,
 
+    /// output autoregressive series x[t] = rho*x[t-1] + sqrt(1-rho^2)*sample[t] with the specified rho between -1 and 1,
+    /// i.e. temporally correlated noise with the same variance as the samples. The series starts with the first sample.
+    /// Cannot be combined with --cumulative, which is the limiting case of rho=1 without the sqrt(1-rho^2) factor
+    #[argh(option)]
+    ar1: Option<f64>,
+
+    /// replace each sample with the mean of the last specified number of samples (after the transforms,
+    /// but before --cumulative or --ar1), producing smoother, band-limited noise.
+    /// The first outputs are averages of fewer samples, as there are no earlier samples yet
+    #[argh(option)]
+    smooth: Option<usize>,
+
+    /// output differences between consecutive values instead of the values, undoing --cumulative of an earlier run.
+    /// Applied last, e.g. after --ar1 and --reflect. The first output is the first value itself
+    #[argh(switch)]
+    diff: bool,
+
     /// use specified seed instead for PRNG
     #[argh(option,short='S')]
     seed: Option<u64>,
 
+    /// use seed derived from the specified string (using FNV-1a hash) instead for PRNG
+    #[argh(option)]
+    seed_string: Option<String>,
+
+    /// pseudorandom number generator algorithm: small, chacha8, chacha12, chacha20, pcg64 or philox.
+    /// Default is small, which is fast, but its output for a given seed may differ between platforms and versions of this program.
+    /// Philox is counter-based: the n-th random word is a function of the seed and n, the seed being used as the key directly
+    #[argh(option,default="RngAlgorithm::Small")]
+    rng: RngAlgorithm,
+
+    /// print the seed used for PRNG to stderr as `seed=<value>`, so that the run can be reproduced with --seed
+    #[argh(switch)]
+    print_seed: bool,
+
     /// output as binary numbers of specified format instead of text.
     /// Valid formats are f{{32,64}}{{be,le}}, {{u,s}}8, {{u,s}}{{16,32,64}}{{le,be}}.
-    /// Out of range values are clamped to valid ranges
+    /// Fractional parts are truncated for integer formats unless --rounding is given. Out of range values, including infinities, are clamped
+    /// to valid ranges (to the largest finite value for f32). NaN is kept as is in floating point formats
+    /// and is an error in integer formats
     #[argh(option,short='b')]
     binary_format: Option<BinaryFormat>,
 
+    /// how to convert values to integer binary formats: trunc (default, toward zero), nearest (half away from zero)
+    /// or stochastic (round up with probability equal to the fractional part, which preserves the mean, e.g. for dithering).
+    /// Stochastic rounding uses a separate PRNG, so the samples themselves are the same as with other modes
+    #[argh(option)]
+    rounding: Option<Rounding>,
+
+    /// clamp values to the specified range `lo,hi` and map it linearly to the full range of the integer --binary-format,
+    /// e.g. `--map-range -4,4 -b s16le` for normal noise as audio samples. Applied before --rounding
+    #[argh(option)]
+    map_range: Option<String>,
+
+    /// prepend a WAV file header to binary output, so it can be played as audio (requires -n and -b u8, s16le, s32le,
+    /// f32le or f64le). Each line is a frame, i.e. --columns or dimensions of a vector-valued distribution are channels
+    #[argh(switch)]
+    wav: bool,
+
+    /// sample rate written to the WAV header of --wav, in frames per second. Defaults to 44100
+    #[argh(option)]
+    sample_rate: Option<u32>,
+
+    /// write output to the specified file instead of stdout
+    #[argh(option,short='o')]
+    output: Option<std::path::PathBuf>,
+
+    /// with -o, append to the file instead of overwriting it
+    #[argh(switch)]
+    append: bool,
+
+    /// size of the output buffer in bytes. Larger buffers reduce the number of write calls with fast binary output
+    #[argh(option, default="32768")]
+    buffer_size: usize,
+
+    /// flush the output buffer after every specified number of samples (lines), so that consumers of a live stream
+    /// do not receive it in bursts
+    #[argh(option)]
+    flush_every: Option<u64>,
+
+    /// flush the output after every sample (line), same as --flush-every 1
+    #[argh(switch)]
+    unbuffered: bool,
+
     /// number of sampels to generate, instead of an infinite stream
     #[argh(option,short='n')]
     num_samples: Option<u64>,
 
+    /// do not output the specified number of first samples (lines), e.g. as burn-in period of --ar1 or to continue
+    /// a seeded stream from a known offset. Skipped samples still go through all transforms and accumulators,
+    /// but do not count towards -n, --stats or --histogram
+    #[argh(option)]
+    skip: Option<u64>,
+
+    /// output only the first of each consecutive group of the specified number of samples (lines), after --skip.
+    /// -n counts output samples, so n*thin samples are generated
+    #[argh(option)]
+    thin: Option<u64>,
+
+    /// group output into records of the specified number of samples (lines), each followed by an empty line in text mode
+    /// or by --block-marker in binary mode. If -n is not a multiple of the block size, the last record is incomplete and
+    /// is not followed by the separator
+    #[argh(option)]
+    block: Option<u64>,
+
+    /// with --block and --binary-format, bytes to write after each record, as hexadecimal string, e.g. ff00. Default is none
+    #[argh(option)]
+    block_marker: Option<String>,
+
+    /// number of independent samples to output on each line (or back-to-back in binary mode).
+    /// With --cumulative, each column is accumulated separately
+    #[argh(option,default="1",short='k')]
+    columns: usize,
+
+    /// for compound distributions, output the latent variable after each sample on the same line: lambda for gammapoisson,
+    /// index of the component starting from 0 for mixture
+    #[argh(switch)]
+    show_latent: bool,
+
+    /// replace each sample of the distribution with 0 with the specified probability from 0 to 1 (exclusive),
+    /// e.g. zero-inflated poisson or negbinomial for count data with excess zeros. Applied before all transforms
+    #[argh(option)]
+    zero_inflate: Option<f64>,
+
+    /// separator between values on the same line in text mode. Default is space, or comma with --csv
+    #[argh(option)]
+    delimiter: Option<String>,
+
+    /// output comma-separated values
+    #[argh(switch)]
+    csv: bool,
+
+    /// end lines of text output with CR LF instead of LF
+    #[argh(switch)]
+    crlf: bool,
+
+    /// start each line of text output with zero-based index of the sample (line), followed by the delimiter
+    #[argh(switch)]
+    index: bool,
+
+    /// print values in exponential notation, e.g. 1.5000000000e3, with --precision digits after decimal point
+    #[argh(switch,short='E')]
+    scientific: bool,
+
+    /// print values in exact hexadecimal floating point format, like C's `%a`, e.g. 0x1.8p+1 for 3. Ignores --precision
+    #[argh(switch)]
+    hex_float: bool,
+
+    /// output newline-delimited JSON: a number per line, or an array per line for multiple columns and vector-valued distributions.
+    /// Non-finite values are written as null
+    #[argh(switch)]
+    json: bool,
+
+    /// replace each sample with its absolute value, producing half-normal instead of normal distribution, half-Cauchy instead of Cauchy, etc.
+    /// Applied before all other transforms, including --exponentiate
+    #[argh(switch)]
+    abs: bool,
+
     /// exponentiate (e^x) each sample, producing log-normal instead of normal distribution, log-Cauchy instead of Cauchy, etc.
     #[argh(switch,short='e')]
     exponentiate: bool,
 
+    /// exponentiate each sample with the specified base (b^x) instead of e, e.g. 10 for log10-normal instead of log-normal.
+    /// Implies --exponentiate and is applied at the same point, after --abs and before --scale
+    #[argh(option)]
+    exp_base: Option<f64>,
+
+    /// multiply each sample by the specified value. Applied after --exponentiate, but before the discard checks and --cumulative
+    #[argh(option,default="1.0")]
+    scale: f64,
+
+    /// add the specified value to each sample. Applied after --scale
+    #[argh(option,default="0.0")]
+    offset: f64,
+
+    /// round each sample to the nearest multiple of the specified step. Applied after --offset, but before the discard checks
+    #[argh(option)]
+    round: Option<f64>,
+
+    /// wrap each sample into the range from 0 to the specified number (exclusive) by Euclidean remainder, after --round,
+    /// e.g. to map counts to bucket ids. Negative samples wrap around from the top, e.g. -1 becomes m-1.
+    /// Non-integer samples keep their fractional part, use --round 1 first to wrap whole numbers only
+    #[argh(option)]
+    modulo: Option<f64>,
+
     /// discard samples that are below the specified value
     #[argh(option,short='L')]
     discard_below: Option<f64>,
@@ -65,488 +274,1423 @@ struct Opts {
     #[argh(option,short='H')]
     discard_above: Option<f64>,
 
-    #[argh(subcommand)]
-    distribution : Distributions,
-}
+    /// with -L/-H, replace out-of-range samples with the nearest limit instead of discarding them, in text and binary output alike.
+    /// If only one of the limits is specified, only that side is clamped. Values of --cumulative, --ar1 and --smooth
+    /// are clamped again, so e.g. a random walk sticks to the limits and every output value is in the range,
+    /// except for --diff, which outputs differences of the clamped values
+    #[argh(switch)]
+    clamp: bool,
 
-#[derive(argh::FromArgs)]
-#[argh(subcommand)]
-enum Distributions {
-    Uniform(Uniform),
-    Normal(Normal),
-    Cauchy(Cauchy),
-    Triangular(Triangular),
-    StudentsT(StudentsT),
-    Stable(Stable),
-    Empirical(Empirical),
-    Categorical(Categorical),
-    Beta(Beta),
-    Binomial(Binomial),
-    DiscreteUniform(DiscreteUniform),
-    ChiSquared(ChiSquared),
-    Chi(Chi),
-    Gamma(Gamma),
-    Exp(Exp),
-    FisherSnedecor(FisherSnedecor),
-    Geometric(Geometric),
-    Hypergeometric(Hypergeometric),
-    InverseGamma(InverseGamma),
-    Laplace(Laplace),
-    Pareto(Pareto),
-    Poisson(Poisson),
-    Weibull(Weibull),
-}
-
-trait DistributionObject {
-    fn sample(&self, rng: &mut rand::rngs::SmallRng) -> f64;
-}
-impl<T: rand::distributions::Distribution<f64>> DistributionObject for T {
-    fn sample(&self, rng: &mut rand::rngs::SmallRng) -> f64 {
-        rand::distributions::Distribution::sample(self, rng)
-    }
-}
-
-/// Uniform distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="uniform")]
-struct Uniform {
-    /// include specified maximum value as possible candidate for generation
+    /// with -L/-H, mirror out-of-range values about the violated limit instead of discarding them.
+    /// A value that overshoots the other limit after mirroring is mirrored again, until it gets into the range.
+    /// With --cumulative, the accumulated value is reflected, producing a walk that bounces off the limits
     #[argh(switch)]
-    right_inclusive: bool,
+    reflect: bool,
 
-    #[argh(positional)]
-    min: f64,
+    /// exit with an error if any sample is outside the specified range `lo,hi` (inclusive), e.g. to check in tests
+    /// that a distribution stays within its support. Checked after the transforms above, --reflect, --clamp, --equalize,
+    /// --cumulative and other series options, except for --diff
+    #[argh(option)]
+    assert_range: Option<String>,
 
-    #[argh(positional)]
-    max: f64,
-}
+    /// discard NaN and infinite samples, e.g. from cauchy or stable distributions with extreme parameters.
+    /// Checked after all other transforms, so e.g. infinity can still be clamped by --clamp
+    #[argh(switch)]
+    drop_nonfinite: bool,
 
-/// Normal, Gaussian distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="normal")]
-struct Normal {
-    #[argh(positional)]
-    mean: f64,
+    /// temper heavy tails by discarding each sample with probability 1-exp(-lambda*|x|), checked after the other
+    /// transforms. E.g. turns cauchy or stable into tempered distributions with finite variance. 0 means no tempering
+    #[argh(option)]
+    temper: Option<f64>,
 
-    #[argh(positional)]
-    std_dev: f64,
-}
+    /// output antithetic variates: each pair of consecutive samples is generated from the same uniform number `u`,
+    /// as F^-1(u) and F^-1(1-u), to reduce variance of Monte Carlo estimates. Supported for uniform, normal,
+    /// lognormal, logitnormal, truncatednormal, cauchy, triangular, exp, laplace, tukeylambda, empiricalcdf, pareto,
+    /// weibull, gumbel, rayleigh and frechet
+    #[argh(switch)]
+    antithetic: bool,
 
-/// Cauchy, Lorentz distribution - fat-tailed and continuous
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="cauchy")]
-struct Cauchy {
-    #[argh(positional)]
-    location: f64,
+    /// drive inverse CDF of the distribution with the Halton low-discrepancy sequence instead of the PRNG,
+    /// for faster converging numerical integration. With --columns, each line is a point of a multidimensional sequence.
+    /// The distribution itself does not use the PRNG, so the output is the same for any --seed, unless --zero-inflate,
+    /// --temper or --rounding stochastic is also specified, as these still draw from the PRNG.
+    /// Supported for the same distributions as --antithetic
+    #[argh(switch)]
+    quasirandom: bool,
 
-    #[argh(positional)]
-    scale: f64,
-}
+    /// print numbers of accepted and discarded samples to stderr when the stream ends (requires -n)
+    #[argh(switch)]
+    report_discards: bool,
 
+    /// print numbers of samples (lines) drawn from the distribution and output, their ratio, and the number of bytes
+    /// taken from the PRNG to stderr when the stream ends (requires -n). Draws include samples that were discarded,
+    /// skipped or thinned out
+    #[argh(switch)]
+    n_effective: bool,
 
-/// Triangular distribution - continuous
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="triangular")]
-struct Triangular {
-    #[argh(positional)]
-    min: f64,
+    /// print count, min, max, mean and standard deviation of the output values to stderr when the stream ends (requires -n)
+    #[argh(switch)]
+    stats: bool,
 
+    /// instead of the samples, print a text histogram of them with the specified number of equal-width bins (requires -n).
+    /// All samples are kept in memory until the end
+    #[argh(option)]
+    histogram: Option<usize>,
 
-    #[argh(positional)]
-    mode: f64,
+    /// replace each sample by its rank among all samples of the same column divided by their number (requires -n),
+    /// turning any distribution into a uniform one on (0, 1]. Tied samples get their average rank.
+    /// All samples are generated and sorted before the output starts
+    #[argh(switch)]
+    equalize: bool,
+
+    /// limit output to the specified number of samples (lines) per second. 0 means unlimited
+    #[argh(option)]
+    rate: Option<f64>,
+
+    /// wait for the specified number of seconds before generating output, e.g. to let a consumer of -o get ready
+    #[argh(option)]
+    delay: Option<f64>,
+
+    /// generate binary output (requires -n and -b) in the specified number of threads. Each thread uses its own PRNG
+    /// seeded with seed+thread_index and generates every N-th block of 65536 lines, so the output is reproducible
+    /// for the same seed and number of threads, but differs between different numbers of threads.
+    /// With --rng philox, each block is generated from its own position of the stream for the seed instead,
+    /// so the output is the same for any number of threads, but differs from the output without --threads
+    #[argh(option)]
+    threads: Option<usize>,
+
+    /// print program version and versions of generation algorithms that changed in the past, then exit
+    #[argh(switch,short='V')]
+    version: bool,
+
+    /// instead of a distribution subcommand, read parameters of the specified distribution from stdin, one set per line
+    /// as they would be given after the subcommand name, e.g. `0 1` for `--params-stdin normal`.
+    /// Outputs -n samples (lines), 1 by default, for each parameter line
+    #[argh(option)]
+    params_stdin: Option<String>,
 
-    #[argh(positional)]
-    max: f64,
+    #[argh(subcommand)]
+    distribution : Option<Distributions>,
 }
 
-
-/// Student-T distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="studentt")]
-struct StudentsT {
-    #[argh(positional)]
-    location: f64,
-
-
-    #[argh(positional)]
-    scale: f64,
-
-    #[argh(positional)]
-    freedom: f64,
+/// Parameter lines of --params-stdin
+struct ParamsReader {
+    name: String,
+    lines: std::io::Lines<std::io::StdinLock<'static>>,
+    line_number: usize,
 }
 
-/// General case of stable continuous distribution, generated by CMS method.
-/// Note that version 0.1 of this program used nonstandard distribution when alpha was not 1.0.
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="stable")]
-struct Stable {
-    #[argh(positional)]
-    location: f64,
-
+impl ParamsReader {
+    fn new(name: String) -> Self {
+        Self { name, lines: std::io::BufRead::lines(std::io::stdin().lock()), line_number: 0 }
+    }
 
-    #[argh(positional)]
-    scale: f64,
+    /// Parse the next non-empty line, or return `None` at the end of input
+    fn next_distribution(&mut self) -> anyhow::Result<Option<Distributions>> {
+        for line in &mut self.lines {
+            self.line_number += 1;
+            let line = line.context("Failed to read stdin")?;
+            let args : Vec<&str> = line.split_whitespace().collect();
+            if args.is_empty() {
+                continue;
+            }
+            return parse_args(&self.name, &args)
+                .map(Some)
+                .with_context(|| format!("Invalid parameters on stdin line {}", self.line_number));
+        }
+        Ok(None)
+    }
+}
 
-    #[argh(positional)]
-    alpha: f64,
+/// Replace each of the values by its rank divided by the number of values, from 1/n to 1.
+/// Equal values get their average rank
+fn equalize(values: &mut [f64]) {
+    let mut order : Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let n = values.len() as f64;
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i + 1;
+        while j < order.len() && values[order[j]] == values[order[i]] {
+            j += 1;
+        }
+        // Average of 1-based ranks i+1 to j
+        let rank = (i + 1 + j) as f64 / 2.0;
+        for &k in &order[i..j] {
+            ranks[k] = rank / n;
+        }
+        i = j;
+    }
+    values.copy_from_slice(&ranks);
+}
 
-    #[argh(positional)]
-    beta: f64,
+/// Write RIFF header of a WAV file with the specified number of frames
+fn write_wav_header(so: &mut impl Write, format: BinaryFormat, channels: usize, sample_rate: u32, frames: u64) -> anyhow::Result<()> {
+    // WAVE_FORMAT_PCM is 1 and WAVE_FORMAT_IEEE_FLOAT is 3
+    let (tag, bytes) : (u16, u16) = match format {
+        BinaryFormat::U8 => (1, 1),
+        BinaryFormat::S16LE => (1, 2),
+        BinaryFormat::S32LE => (1, 4),
+        BinaryFormat::F32LE => (3, 4),
+        BinaryFormat::F64LE => (3, 8),
+        _ => anyhow::bail!("--wav requires -b u8, s16le, s32le, f32le or f64le"),
+    };
+    let channels = u16::try_from(channels).context("Too many channels for --wav")?;
+    let block_align = channels.checked_mul(bytes).context("Too many channels for --wav")?;
+    let data_len = frames.checked_mul(block_align as u64)
+        .and_then(|len| u32::try_from(len).ok())
+        .filter(|len| *len <= u32::MAX - 36)
+        .context("Too many samples for --wav, which is limited to 4 GiB")?;
+    so.write_all(b"RIFF")?;
+    so.write_u32::<LE>(36 + data_len)?;
+    so.write_all(b"WAVEfmt ")?;
+    so.write_u32::<LE>(16)?;
+    so.write_u16::<LE>(tag)?;
+    so.write_u16::<LE>(channels)?;
+    so.write_u32::<LE>(sample_rate)?;
+    so.write_u32::<LE>(sample_rate.saturating_mul(block_align as u32))?;
+    so.write_u16::<LE>(block_align)?;
+    so.write_u16::<LE>(bytes * 8)?;
+    so.write_all(b"data")?;
+    so.write_u32::<LE>(data_len)?;
+    Ok(())
 }
 
+/// Parse `lo,hi` of --map-range
+fn parse_range(s: &str) -> anyhow::Result<(f64, f64)> {
+    let (lo, hi) = s.split_once(',').ok_or_else(|| anyhow::anyhow!("Expected lo,hi"))?;
+    let (lo, hi) : (f64, f64) = (lo.trim().parse()?, hi.trim().parse()?);
+    if !(lo.is_finite() && hi.is_finite() && lo < hi) {
+        anyhow::bail!("Expected finite lo below hi");
+    }
+    Ok((lo, hi))
+}
 
-/// Discrete distribution that just endlessly randomly selects one of specified values
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="empirical")]
-struct Empirical {
-    #[argh(positional)]
-    data_points: Vec<f64>,
+/// Error for --assert-range if the value of the specified zero-based sample index is outside the range
+fn check_range(x: f64, index: usize, row_len: usize, range: Option<(f64, f64)>) -> anyhow::Result<()> {
+    if let Some((lo, hi)) = range {
+        if !(lo <= x && x <= hi) {
+            anyhow::bail!("Sample {} (column {}) is {}, outside of --assert-range {},{}", index / row_len, index % row_len, x, lo, hi);
+        }
+    }
+    Ok(())
 }
 
+/// Error if the --round step is not a positive finite number, which would turn all samples into NaN
+fn check_round_step(step: f64) -> anyhow::Result<()> {
+    if !(step > 0.0 && step.is_finite()) {
+        anyhow::bail!("--round step must be positive and finite, got {}", step);
+    }
+    Ok(())
+}
 
-/// Discrete distribution that generates values according to specified probabilities 
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="categorical")]
-struct Categorical {
-    #[argh(positional)]
-    probabilities: Vec<f64>,
+/// Clamp the value to range `from` and map it linearly to range `to`
+fn map_range(c: f64, from: (f64, f64), to: (f64, f64)) -> f64 {
+    to.0 + (c.clamp(from.0, from.1) - from.0) / (from.1 - from.0) * (to.1 - to.0)
 }
 
-/// Beta distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="beta")]
-struct Beta {
-    #[argh(positional)]
-    shape_a: f64,
+/// Round the value to an integer before writing it in integer binary format
+fn apply_rounding(c: f64, rounding: Rounding, rng: &mut dyn rand::RngCore) -> f64 {
+    match rounding {
+        Rounding::Trunc => c.trunc(),
+        Rounding::Nearest => c.round(),
+        Rounding::Stochastic => (c + rand::Rng::gen::<f64>(rng)).floor(),
+    }
+}
 
-    #[argh(positional)]
-    shape_b: f64,
+/// Clamp the value to the range of a binary integer format and truncate its fractional part.
+/// There is no integer to clamp NaN to, so it is an error
+fn to_integer(c: f64, min: f64, max: f64) -> std::io::Result<f64> {
+    if c.is_nan() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "NaN cannot be written in integer binary format, use --drop-nonfinite to skip it"));
+    }
+    Ok(c.clamp(min, max).trunc())
 }
 
-/// Binomial distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="binomial")]
-struct Binomial {
-    #[argh(positional)]
-    probability: f64,
+/// Finite values beyond the range of f32 are clamped to the largest finite f32, infinities and NaN are kept
+fn to_f32(c: f64) -> f32 {
+    if c.is_finite() {
+        c.clamp(f32::MIN as f64, f32::MAX as f64) as f32
+    } else {
+        c as f32
+    }
+}
 
-    #[argh(positional)]
-    trials: u64,
+fn write_binary(so: &mut impl Write, format: &BinaryFormat, c: f64) -> std::io::Result<()> {
+    match format {
+        BinaryFormat::F32LE => so.write_f32::<LE>(to_f32(c)),
+        BinaryFormat::F32BE => so.write_f32::<BE>(to_f32(c)),
+        BinaryFormat::F64LE => so.write_f64::<LE>(c),
+        BinaryFormat::F64BE => so.write_f64::<BE>(c),
+        BinaryFormat::S8 => so.write_i8(to_integer(c, i8::MIN as f64, i8::MAX as f64)? as i8),
+        BinaryFormat::U8 => so.write_u8(to_integer(c, 0.0, u8::MAX as f64)? as u8),
+        BinaryFormat::S16LE => so.write_i16::<LE>(to_integer(c, i16::MIN as f64, i16::MAX as f64)? as i16),
+        BinaryFormat::S16BE => so.write_i16::<BE>(to_integer(c, i16::MIN as f64, i16::MAX as f64)? as i16),
+        BinaryFormat::U16LE => so.write_u16::<LE>(to_integer(c, 0.0, u16::MAX as f64)? as u16),
+        BinaryFormat::U16BE => so.write_u16::<BE>(to_integer(c, 0.0, u16::MAX as f64)? as u16),
+        BinaryFormat::S32LE => so.write_i32::<LE>(to_integer(c, i32::MIN as f64, i32::MAX as f64)? as i32),
+        BinaryFormat::S32BE => so.write_i32::<BE>(to_integer(c, i32::MIN as f64, i32::MAX as f64)? as i32),
+        BinaryFormat::U32LE => so.write_u32::<LE>(to_integer(c, 0.0, u32::MAX as f64)? as u32),
+        BinaryFormat::U32BE => so.write_u32::<BE>(to_integer(c, 0.0, u32::MAX as f64)? as u32),
+        // i64::MAX and u64::MAX are not representable in f64 and round up, which `as` then saturates
+        BinaryFormat::S64LE => so.write_i64::<LE>(to_integer(c, i64::MIN as f64, i64::MAX as f64)? as i64),
+        BinaryFormat::S64BE => so.write_i64::<BE>(to_integer(c, i64::MIN as f64, i64::MAX as f64)? as i64),
+        BinaryFormat::U64LE => so.write_u64::<LE>(to_integer(c, 0.0, u64::MAX as f64)? as u64),
+        BinaryFormat::U64BE => so.write_u64::<BE>(to_integer(c, 0.0, u64::MAX as f64)? as u64),
+    }
 }
 
-/// Discrete uniform, generates integer numbers from min to max, both inclusive
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="discreteuniform")]
-struct DiscreteUniform {
-    #[argh(positional)]
-    min: i64,
+/// Number of lines generated by a thread at once with --threads
+const THREAD_BLOCK_ROWS : u64 = 65536;
 
-    #[argh(positional)]
-    max: i64,
+/// Philox stream positioned at the start of the specified block of --threads output.
+/// Each block gets 2^64 words of the stream, which it cannot run out of
+fn philox_for_block(key: u64, block: u64) -> Box<dyn rand::RngCore> {
+    let mut rng = Philox4x32::new(key);
+    rng.seek((block as u128) << 64);
+    Box::new(rng)
 }
 
-/// Chi-squared distribution which is a special case of the Gamma distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="chisquared")]
-struct ChiSquared {
-    #[argh(positional)]
-    freedom: f64,
+fn make_rng(algorithm: RngAlgorithm, seed: Option<u64>) -> Box<dyn rand::RngCore> {
+    match algorithm {
+        RngAlgorithm::Small => new_rng::<rand::rngs::SmallRng>(seed),
+        RngAlgorithm::ChaCha8 => new_rng::<rand_chacha::ChaCha8Rng>(seed),
+        RngAlgorithm::ChaCha12 => new_rng::<rand_chacha::ChaCha12Rng>(seed),
+        RngAlgorithm::ChaCha20 => new_rng::<rand_chacha::ChaCha20Rng>(seed),
+        RngAlgorithm::Pcg64 => new_rng::<rand_pcg::Pcg64>(seed),
+        RngAlgorithm::Philox => new_rng::<Philox4x32>(seed),
+    }
 }
 
+fn make_sampler(distribution: Distributions, antithetic: bool, quasirandom: bool, columns: usize, show_latent: bool, zero_inflate: Option<f64>) -> anyhow::Result<Sampler> {
+    if let Some(p) = zero_inflate {
+        return match make_sampler(distribution, antithetic, quasirandom, columns, show_latent, None)? {
+            Sampler::Scalar(d) => Ok(Sampler::Scalar(Box::new(ZeroInflated::new(d, p)))),
+            Sampler::Vector(_) => anyhow::bail!("--zero-inflate cannot be used with vector-valued distributions or --show-latent"),
+        };
+    }
+    if show_latent {
+        if antithetic || quasirandom {
+            anyhow::bail!("--show-latent cannot be used with --antithetic or --quasirandom");
+        }
+        return build_sampler(distribution)?.with_latent().context("--show-latent cannot be used with this distribution");
+    }
+    Ok(if quasirandom {
+        let quantile = build_quantile_function(distribution).context("--quasirandom cannot be used with this distribution")?;
+        Sampler::Scalar(Box::new(Halton::new(quantile, columns.max(1))))
+    } else if antithetic {
+        let quantile = build_quantile_function(distribution).context("--antithetic cannot be used with this distribution")?;
+        Sampler::Scalar(Box::new(Antithetic::new(quantile)))
+    } else {
+        build_sampler(distribution)?
+    })
+}
 
-/// Chi distribution 
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="chi")]
-struct Chi {
-    #[argh(positional)]
-    freedom: f64,
+/// PRNG wrapper counting the bytes taken from it
+struct CountingRng {
+    inner: Box<dyn rand::RngCore>,
+    bytes: std::rc::Rc<std::cell::Cell<u64>>,
 }
 
-/// Gamma distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="gamma")]
-struct Gamma {
-    #[argh(positional)]
-    shape: f64,
+impl rand::RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.bytes.set(self.bytes.get() + 4);
+        self.inner.next_u32()
+    }
 
-    #[argh(positional)]
-    rate: f64,
-}
+    fn next_u64(&mut self) -> u64 {
+        self.bytes.set(self.bytes.get() + 8);
+        self.inner.next_u64()
+    }
 
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.bytes.set(self.bytes.get() + dest.len() as u64);
+        self.inner.fill_bytes(dest)
+    }
 
-/// Exponential distribution and is a special case of the Gamma distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="exp")]
-struct Exp {
-    #[argh(positional)]
-    rate: f64,
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.bytes.set(self.bytes.get() + dest.len() as u64);
+        self.inner.try_fill_bytes(dest)
+    }
 }
 
-/// Fisher-Snedecor distribution also commonly known as the F-distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="fisher")]
-struct FisherSnedecor {
-    #[argh(positional)]
-    freedom_1: f64,
-
-    #[argh(positional)]
-    freedom_2: f64,
+fn new_rng<R: rand::RngCore + SeedableRng + 'static>(seed: Option<u64>) -> Box<dyn rand::RngCore> {
+    match seed {
+        Some(s) => Box::new(R::seed_from_u64(s)),
+        None => Box::new(R::from_entropy()),
+    }
 }
 
-/// Geometric distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="geometric")]
-struct Geometric {
-    #[argh(positional)]
-    probability: f64,
+/// Running mean of the last `window` values
+struct MovingAverage {
+    window: usize,
+    values: std::collections::VecDeque<f64>,
+    sum: f64,
 }
 
-/// Hypergeometric distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="hypergeometric")]
-struct Hypergeometric {
-    #[argh(positional)]
-    population: u64,
+impl MovingAverage {
+    fn new(window: usize) -> Self {
+        Self { window, values: std::collections::VecDeque::with_capacity(window), sum: 0.0 }
+    }
 
-    #[argh(positional)]
-    successes: u64,
+    fn push(&mut self, x: f64) -> f64 {
+        if self.values.len() == self.window {
+            self.sum -= self.values.pop_front().unwrap();
+        }
+        self.values.push_back(x);
+        self.sum += x;
+        self.sum / self.values.len() as f64
+    }
+}
 
-    #[argh(positional)]
-    draws: u64,
+/// Running statistics of output values, using Welford's algorithm
+#[derive(Default)]
+struct Stats {
+    count: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
 }
 
-/// Inverse Gamma distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="inversegamma")]
-struct InverseGamma {
-    #[argh(positional)]
-    shape: f64,
+impl Stats {
+    fn add(&mut self, x: f64) {
+        if self.count == 0 {
+            self.min = x;
+            self.max = x;
+        } else {
+            self.min = self.min.min(x);
+            self.max = self.max.max(x);
+        }
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
 
-    #[argh(positional)]
-    rate: f64,
+    /// Sample (Bessel-corrected) standard deviation
+    fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        (self.m2 / (self.count - 1) as f64).sqrt()
+    }
 }
 
-/// Laplace distribution.
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="laplace")]
-struct Laplace {
-    #[argh(positional)]
-    location: f64,
+/// Summary line for --stats. Without values, min, max and mean are NaN instead of made up zeros
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (min, max, mean) = if self.count == 0 { (f64::NAN, f64::NAN, f64::NAN) } else { (self.min, self.max, self.mean) };
+        write!(f, "count={} min={} max={} mean={} stddev={}", self.count, min, max, mean, self.std_dev())
+    }
+}
 
-    #[argh(positional)]
-    scale: f64,
+/// Text histogram of finite values, with each line showing bin boundaries, count and a bar
+fn print_histogram(so: &mut impl Write, values: &[f64], bins: usize, precision: usize, line_ending: &str) -> std::io::Result<()> {
+    const BAR_WIDTH : usize = 60;
+    let finite = || values.iter().copied().filter(|x| x.is_finite());
+    let min = finite().fold(f64::INFINITY, f64::min);
+    let max = finite().fold(f64::NEG_INFINITY, f64::max);
+    if min > max {
+        return Ok(());
+    }
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0u64; bins];
+    for x in finite() {
+        let i = if width > 0.0 { ((x - min) / width) as usize } else { 0 };
+        counts[i.min(bins - 1)] += 1;
+    }
+    let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+    for (i, &count) in counts.iter().enumerate() {
+        let lo = min + width * i as f64;
+        let hi = if i + 1 == bins { max } else { min + width * (i + 1) as f64 };
+        let bar = "#".repeat((count as usize * BAR_WIDTH).div_ceil(max_count as usize));
+        write!(so, "{:.*} {:.*} {} {}{}", precision, lo, precision, hi, count, bar, line_ending)?;
+    }
+    Ok(())
 }
 
-/// Pareto distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="pareto")]
-struct Pareto {
-    #[argh(positional)]
-    scale: f64,
+/// Mirror `x` about the violated limit, repeatedly, until it gets into the `[lo, hi]` range
+fn reflect(x: f64, lo: Option<f64>, hi: Option<f64>) -> f64 {
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => {
+            let width = hi - lo;
+            let y = (x - lo).rem_euclid(2.0 * width);
+            lo + if y > width { 2.0 * width - y } else { y }
+        }
+        (Some(lo), None) if x < lo => 2.0 * lo - x,
+        (None, Some(hi)) if x > hi => 2.0 * hi - x,
+        _ => x,
+    }
+}
 
-    #[argh(positional)]
-    shape: f64,
+/// Replace `x` with the violated limit, if any
+fn clamp(x: f64, lo: Option<f64>, hi: Option<f64>) -> f64 {
+    match (lo, hi) {
+        (Some(lo), _) if x < lo => lo,
+        (_, Some(hi)) if x > hi => hi,
+        _ => x,
+    }
 }
 
+/// 64-bit FNV-1a hash, used to turn `--seed-string` into a seed
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut h : u64 = 0xcbf29ce484222325;
+    for &b in data {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
 
-/// Poisson distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="poisson")]
-struct Poisson {
-    #[argh(positional)]
-    lambda: f64,
+/// Parse string of hexadecimal digit pairs, like `ff00`, into bytes
+fn parse_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        anyhow::bail!("expected even number of hexadecimal digits");
+    }
+    (0..s.len()).step_by(2).map(|i| Ok(u8::from_str_radix(&s[i..i + 2], 16)?)).collect()
 }
 
-/// Weibull distribution
-#[derive(argh::FromArgs)]
-#[argh(subcommand, name="weibull")]
-struct Weibull {
-    #[argh(positional)]
-    shape: f64,
+/// Digits after the decimal point when --precision is not specified: none if all output values are integers.
+/// Exponential notation needs them even for integers, e.g. 12345 would be printed as 1e4
+fn default_precision(opts: &Opts, distribution: &Distributions) -> usize {
+    let exponentiate = opts.exponentiate || opts.exp_base.is_some();
+    // Gamma-Poisson lambda is not an integer, unlike index of a mixture component
+    let latent_is_integer = !opts.show_latent || matches!(distribution, Distributions::Mixture(_));
+    // Clamping or reflecting at a fractional limit produces fractional values
+    let limits_are_integer = !(opts.clamp || opts.reflect)
+        || [opts.discard_below, opts.discard_above].iter().flatten().all(|limit| limit.fract() == 0.0);
+    let integer_valued = opts.ar1.is_none() && opts.smooth.is_none() && !opts.equalize && latent_is_integer && limits_are_integer
+        && opts.modulo.is_none_or(|m| m.fract() == 0.0) && match opts.round {
+        Some(step) => step.fract() == 0.0,
+        None => distribution.is_integer_valued() && !exponentiate && opts.scale.fract() == 0.0 && opts.offset.fract() == 0.0,
+    };
+    if integer_valued && !opts.scientific { 0 } else { 10 }
+}
 
-    #[argh(positional)]
-    scale: f64,
+/// Format the value rounded to `digits` significant digits, in positional notation
+fn significant(x: f64, digits: usize) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return format!("{:.*}", digits.saturating_sub(1), x);
+    }
+    // Exponential format does the rounding, e.g. -1.23e4
+    let e = format!("{:.*e}", digits - 1, x);
+    let (mantissa, exponent) = e.split_once('e').unwrap();
+    let exponent : i64 = exponent.parse().unwrap();
+    let decimals = digits as i64 - 1 - exponent;
+    if decimals >= 0 {
+        format!("{:.*}", decimals as usize, x)
+    } else {
+        format!("{}{}", mantissa.replace('.', ""), "0".repeat(-decimals as usize))
+    }
 }
 
-struct StableAlphaNotOne {
-    location: f64,
-    alpha: f64,
-    u_dist: rand::distributions::Uniform<f64>,
-    w_dist: statrs::distribution::Exp,
-    calc_scale: f64,
-    xi: f64,
-    alpha_inv: f64,
-    alpha2: f64,
+/// Format the value like C's `%a` - exact hexadecimal mantissa and binary exponent
+fn hex_float(x: f64) -> String {
+    if x.is_nan() {
+        return "nan".to_owned();
+    }
+    let sign = if x.is_sign_negative() { "-" } else { "" };
+    if x.is_infinite() {
+        return format!("{}inf", sign);
+    }
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mantissa = bits & ((1 << 52) - 1);
+    let (leading, exponent) = match (exponent, mantissa) {
+        (0, 0) => (0, 0),
+        (0, _) => (0, -1022),
+        _ => (1, exponent - 1023),
+    };
+    let digits = format!("{:013x}", mantissa);
+    let digits = digits.trim_end_matches('0');
+    if digits.is_empty() {
+        format!("{}0x{}p{:+}", sign, leading, exponent)
+    } else {
+        format!("{}0x{}.{}p{:+}", sign, leading, digits, exponent)
+    }
 }
 
-impl StableAlphaNotOne {
-    pub fn new(location: f64, scale: f64, alpha: f64, beta: f64) -> Self {
-        let zeta = -beta * (FRAC_PI_2*alpha).tan();
-        Self {
-            location,
-            alpha,
-            u_dist: rand::distributions::Uniform::new(-FRAC_PI_2 + 3.0*f64::EPSILON, FRAC_PI_2 ),
-            w_dist: statrs::distribution::Exp::new(1.0).unwrap(),
-            calc_scale: (zeta*zeta+1.0).powf(0.5/alpha)*scale,
-            xi: (-zeta).atan() / alpha,
-            alpha_inv: 1.0/alpha,
-            alpha2: (1.0 - alpha)/alpha,
+fn write_json_string(so: &mut impl Write, s: &str) -> std::io::Result<()> {
+    write!(so, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(so, "\\\"")?,
+            '\\' => write!(so, "\\\\")?,
+            c if (c as u32) < 0x20 => write!(so, "\\u{:04x}", c as u32)?,
+            c => write!(so, "{}", c)?,
         }
     }
+    write!(so, "\"")
 }
 
-/// Implementation is based on https://en.wikipedia.org/w/index.php?title=Stable_distribution&oldid=1025369901
-impl DistributionObject for StableAlphaNotOne {
-    fn sample(&self, rng: &mut rand::rngs::SmallRng) -> f64 {
-        let u = self.u_dist.sample(rng);
-        let w = self.w_dist.sample(rng);
-        let num1 = (self.alpha*(u + self.xi)).sin();
-        let den1 = u.cos().powf(self.alpha_inv);
-        let num2 = (u - self.alpha * (u + self.xi)).cos() / w;
-        self.location + self.calc_scale * num1 / den1 * (num2).powf(self.alpha2)
+fn main() -> anyhow::Result<()> {
+    let mut opts : Opts = argh::from_env();
+
+    if opts.version {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        println!("stable: Chambers-Mallows-Stuck method with standard parametrization (version 0.1 used nonstandard distribution when alpha was not 1.0)");
+        return Ok(());
     }
-}
 
-struct StableAlphaOne {
-    location: f64,
-    beta: f64,
-    u_dist: rand::distributions::Uniform<f64>,
-    w_dist: statrs::distribution::Exp,
-    calc_scale: f64,
-}
+    if opts.append && opts.output.is_none() {
+        anyhow::bail!("--append requires -o");
+    }
+    if opts.buffer_size < 64 {
+        anyhow::bail!("--buffer-size must be at least 64 bytes");
+    }
+    let exponentiate = opts.exponentiate || opts.exp_base.is_some();
+    if let Some(b) = opts.exp_base {
+        if !(b > 0.0 && b != 1.0 && b.is_finite()) {
+            anyhow::bail!("--exp-base must be positive and not 1, got {}", b);
+        }
+    }
+
+    let flush_every = match (opts.flush_every, opts.unbuffered) {
+        (Some(_), true) => anyhow::bail!("--flush-every and --unbuffered are mutually exclusive"),
+        (Some(0), false) => anyhow::bail!("--flush-every must be at least 1"),
+        (Some(n), false) => Some(n),
+        (None, true) => Some(1),
+        (None, false) => None,
+    };
 
-impl StableAlphaOne {
-    pub fn new(location: f64, scale: f64, beta: f64) -> Self {
-        //scale *= std::f64::consts::FRAC_1_SQRT_2;
-        Self {
-            location: location + FRAC_2_PI * beta * scale * scale.ln(),
-            beta,
-            u_dist: rand::distributions::Uniform::new(-FRAC_PI_2 + 3.0*f64::EPSILON, FRAC_PI_2 ),
-            w_dist: statrs::distribution::Exp::new(1.0).unwrap(),
-            calc_scale: scale * FRAC_2_PI,
+    let mut params = opts.params_stdin.clone().map(ParamsReader::new);
+    let distribution = match (opts.distribution.take(), &mut params) {
+        (Some(_), Some(_)) => anyhow::bail!("--params-stdin cannot be used with a distribution subcommand"),
+        (Some(d), None) => d,
+        (None, Some(params)) => match params.next_distribution()? {
+            Some(d) => d,
+            None => return Ok(()),
+        },
+        (None, None) => anyhow::bail!("Distribution subcommand is required"),
+    };
+    let params_per_line = opts.num_samples.unwrap_or(1);
+    if params.is_some() {
+        if params_per_line == 0 {
+            anyhow::bail!("-n must be at least 1 with --params-stdin");
+        }
+        if opts.threads.is_some() {
+            anyhow::bail!("--params-stdin cannot be used with --threads");
         }
     }
-}
 
-/// Implementation is based on https://en.wikipedia.org/w/index.php?title=Stable_distribution&oldid=1025369901
-impl DistributionObject for StableAlphaOne {
-    fn sample(&self, rng: &mut rand::rngs::SmallRng) -> f64 {
-        let u = self.u_dist.sample(rng);
-        let w = self.w_dist.sample(rng);
-        self.location + self.calc_scale * ( (FRAC_PI_2 + self.beta * u) * u.tan() - self.beta * ( (FRAC_PI_2 * w * u.cos())/(FRAC_PI_2 + self.beta*u) ).ln() )
+    let mut precision = opts.precision.unwrap_or_else(|| default_precision(&opts, &distribution));
+
+    let labels : Option<Vec<String>> = match distribution {
+        Distributions::Categorical(ref c) => c.labels.as_ref().map(|l| l.split(',').map(str::to_owned).collect()),
+        _ => None,
+    };
+    if labels.is_some() {
+        if params.is_some() {
+            anyhow::bail!("--labels cannot be used with --params-stdin");
+        }
+        if opts.binary_format.is_some() {
+            anyhow::bail!("--labels cannot be used with --binary-format");
+        }
+        if opts.histogram.is_some() {
+            anyhow::bail!("--labels cannot be used with --histogram");
+        }
+        let limits_change_values = (opts.clamp || opts.reflect) && (opts.discard_below.is_some() || opts.discard_above.is_some());
+        if opts.cumulative || opts.diff || opts.ar1.is_some() || opts.smooth.is_some() || opts.abs || exponentiate || opts.scale != 1.0 || opts.offset != 0.0
+            || opts.round.is_some() || opts.modulo.is_some() || opts.zero_inflate.is_some() || opts.equalize || limits_change_values {
+            anyhow::bail!("--labels cannot be used with options that change sample values");
+        }
     }
-}
 
+    if opts.show_latent {
+        if opts.histogram.is_some() {
+            anyhow::bail!("--show-latent cannot be used with --histogram");
+        }
+        if opts.cumulative || opts.diff || opts.ar1.is_some() || opts.smooth.is_some() || opts.abs || exponentiate
+            || opts.scale != 1.0 || opts.offset != 0.0 || opts.round.is_some() || opts.modulo.is_some() || opts.discard_below.is_some() || opts.discard_above.is_some() {
+            anyhow::bail!("--show-latent cannot be used with options that change or discard sample values");
+        }
+    }
 
-fn main() -> anyhow::Result<()> {
-    let opts : Opts = argh::from_env();
+    if opts.equalize {
+        if opts.num_samples.is_none() || params.is_some() || opts.threads.is_some() {
+            anyhow::bail!("--equalize requires -n and cannot be used with --params-stdin or --threads");
+        }
+        if opts.cumulative || opts.diff || opts.ar1.is_some() || opts.smooth.is_some() || opts.skip.is_some() || opts.thin.is_some() {
+            anyhow::bail!("--equalize cannot be used with --cumulative, --diff, --ar1, --smooth, --skip or --thin");
+        }
+    }
 
-    let so = std::io::stdout();
-    let so = so.lock();
-    let mut so = std::io::BufWriter::with_capacity(32768, so);
+    if let Some(p) = opts.zero_inflate {
+        if !(0.0..1.0).contains(&p) {
+            anyhow::bail!("--zero-inflate must be at least 0 and below 1, got {}", p);
+        }
+    }
 
-    let d : Box<dyn DistributionObject>;
-    d = match opts.distribution {
-        Distributions::Uniform(Uniform { right_inclusive, min, max }) => {
-            if max <= min {
-                anyhow::bail!("Invalid distribution parameters");
-            }
-            if right_inclusive {
-                Box::new(rand::distributions::Uniform::new_inclusive(min, max))
-            } else {
-                Box::new(rand::distributions::Uniform::new(min, max))
-            }
+    if opts.antithetic && opts.quasirandom {
+        anyhow::bail!("--antithetic and --quasirandom are mutually exclusive");
+    }
+    let thread_distribution = opts.threads.map(|_| distribution.clone());
+    let sampler = make_sampler(distribution, opts.antithetic, opts.quasirandom, opts.columns, opts.show_latent, opts.zero_inflate);
+    let sampler = match params {
+        Some(ref params) => sampler.with_context(|| format!("Invalid parameters on stdin line {}", params.line_number))?,
+        None => sampler?,
+    };
+
+    if let Some(step) = opts.round {
+        check_round_step(step)?;
+    }
+    if let Some(m) = opts.modulo {
+        if !(m > 0.0 && m.is_finite()) {
+            anyhow::bail!("--modulo must be positive, got {}", m);
         }
-        Distributions::Normal(Normal { mean, std_dev }) => Box::new(statrs::distribution::Normal::new(mean, std_dev)?),
-        Distributions::Cauchy(Cauchy { location, scale }) => Box::new(statrs::distribution::Cauchy::new(location, scale)?),
-        Distributions::Triangular(Triangular { min, mode, max }) => Box::new(statrs::distribution::Triangular::new(min,max,mode)?),
-        Distributions::StudentsT(StudentsT { location, scale, freedom }) =>  Box::new(statrs::distribution::StudentsT::new(location,scale,freedom)?),
-        Distributions::Stable(Stable { location, scale, alpha, beta }) => {
-            if alpha < 0.0 || alpha > 2.0 {
-                anyhow::bail!("alpha must be between 0 and 2");
-            }
-            if beta < -1.0 || beta > 1.0 {
-                anyhow::bail!("beta must be between -1 and 1");
-            }
-            if alpha > 0.999 && alpha < 1.001 {
-                Box::new(StableAlphaOne::new(location,scale,beta))
-            } else {
-                Box::new(StableAlphaNotOne::new(location,scale,alpha,beta))
+    }
+    if let Some(rho) = opts.ar1 {
+        if !(-1.0 < rho && rho < 1.0) {
+            anyhow::bail!("--ar1 must be between -1 and 1, got {}", rho);
+        }
+        if opts.cumulative {
+            anyhow::bail!("--ar1 and --cumulative are mutually exclusive");
+        }
+    }
+    if opts.diff && opts.cumulative {
+        anyhow::bail!("--diff and --cumulative are mutually exclusive");
+    }
+    if let Some(lambda) = opts.temper {
+        if lambda.is_nan() || lambda < 0.0 {
+            anyhow::bail!("--temper must not be negative, got {}", lambda);
+        }
+    }
+    if opts.thin == Some(0) {
+        anyhow::bail!("--thin must be at least 1");
+    }
+    if opts.block == Some(0) {
+        anyhow::bail!("--block must be at least 1");
+    }
+    if opts.block.is_some() && (opts.json || opts.histogram.is_some()) {
+        anyhow::bail!("--block cannot be used with --json or --histogram");
+    }
+    let integer_range = opts.binary_format.and_then(|f| f.integer_range());
+    if opts.rounding.is_some() && integer_range.is_none() {
+        anyhow::bail!("--rounding requires an integer --binary-format");
+    }
+    let assert_range = match opts.assert_range {
+        Some(ref s) => Some(parse_range(s).with_context(|| format!("Invalid --assert-range {}", s))?),
+        None => None,
+    };
+    let map_ranges = match opts.map_range {
+        Some(ref s) => {
+            let from = parse_range(s).with_context(|| format!("Invalid --map-range {}", s))?;
+            match integer_range {
+                Some(to) => Some((from, to)),
+                None => anyhow::bail!("--map-range requires an integer --binary-format"),
             }
         }
-        Distributions::Empirical(Empirical { data_points }) => Box::new(statrs::distribution::Empirical::from_vec(data_points)),
-        Distributions::Categorical(Categorical { probabilities }) =>  Box::new(statrs::distribution::Categorical::new(&probabilities)?),
-        Distributions::Beta(Beta { shape_a, shape_b }) => Box::new(statrs::distribution::Beta::new(shape_a,shape_b)?),
-        Distributions::Binomial(Binomial { probability, trials }) => Box::new(statrs::distribution::Binomial::new(probability,trials)?),
-        Distributions::DiscreteUniform(DiscreteUniform { min, max }) => Box::new(statrs::distribution::DiscreteUniform::new(min,max)?),
-        Distributions::ChiSquared(ChiSquared { freedom }) => Box::new(statrs::distribution::ChiSquared::new(freedom)?),
-        Distributions::Chi(Chi { freedom }) => Box::new(statrs::distribution::Chi::new(freedom)?),
-        Distributions::Gamma(Gamma { shape, rate }) => Box::new(statrs::distribution::Gamma::new(shape,rate)?),
-        Distributions::Exp(Exp { rate }) => Box::new(statrs::distribution::Exp::new(rate)?),
-        Distributions::FisherSnedecor(FisherSnedecor { freedom_1, freedom_2 }) => Box::new(statrs::distribution::FisherSnedecor::new(freedom_1,freedom_2)?),
-        Distributions::Geometric(Geometric { probability }) => Box::new(statrs::distribution::Geometric::new(probability)?),
-        Distributions::Hypergeometric(Hypergeometric { population, successes, draws }) => Box::new(statrs::distribution::Hypergeometric::new(population,successes,draws)?),
-        Distributions::InverseGamma(InverseGamma { shape, rate }) => Box::new(statrs::distribution::InverseGamma::new(shape,rate)?),
-        Distributions::Laplace(Laplace { location, scale }) => anyhow::bail!("Laplace distribution is temporarily disabled due to https://github.com/statrs-dev/statrs/issues/150"),
-        Distributions::Pareto(Pareto { scale, shape }) => Box::new(statrs::distribution::Pareto::new(scale,shape)?),
-        Distributions::Poisson(Poisson { lambda }) => Box::new(statrs::distribution::Poisson::new(lambda)?),
-        Distributions::Weibull(Weibull { shape, scale }) => Box::new(statrs::distribution::Weibull::new(shape,scale)?),
+        None => None,
+    };
+    if opts.block_marker.is_some() && (opts.block.is_none() || opts.binary_format.is_none()) {
+        anyhow::bail!("--block-marker requires --block and --binary-format");
+    }
+    let block_marker = match opts.block_marker {
+        Some(ref hex) => parse_hex(hex).with_context(|| format!("Invalid --block-marker {}", hex))?,
+        None => vec![],
     };
+    if opts.smooth == Some(0) {
+        anyhow::bail!("--smooth window must be at least 1");
+    }
+    if opts.clamp && opts.reflect {
+        anyhow::bail!("--clamp and --reflect are mutually exclusive");
+    }
+    if let (Some(lo), Some(hi)) = (opts.discard_below, opts.discard_above) {
+        if lo >= hi {
+            anyhow::bail!("-L must be below -H");
+        }
+    }
+    if opts.report_discards && opts.num_samples.is_none() {
+        anyhow::bail!("--report-discards requires -n");
+    }
+    if opts.n_effective && opts.num_samples.is_none() {
+        anyhow::bail!("--n-effective requires -n");
+    }
+    if opts.stats && opts.num_samples.is_none() {
+        anyhow::bail!("--stats requires -n");
+    }
+    if let Some(bins) = opts.histogram {
+        if bins < 1 {
+            anyhow::bail!("--histogram requires at least 1 bin");
+        }
+        if opts.num_samples.is_none() {
+            anyhow::bail!("--histogram requires -n");
+        }
+        if opts.binary_format.is_some() || opts.json {
+            anyhow::bail!("--histogram cannot be combined with --binary-format or --json");
+        }
+    }
+    if let Some(rate) = opts.rate {
+        if !(rate >= 0.0 && rate.is_finite()) {
+            anyhow::bail!("--rate must be a finite number that is not negative, got {}", rate);
+        }
+        if rate > 0.0 && std::time::Duration::try_from_secs_f64(1.0 / rate).is_err() {
+            anyhow::bail!("--rate is too low, got {:e}", rate);
+        }
+    }
+    if opts.columns < 1 {
+        anyhow::bail!("--columns must be at least 1");
+    }
+    if opts.columns != 1 && matches!(sampler, Sampler::Vector(_)) {
+        anyhow::bail!("--columns cannot be used with vector-valued distributions");
+    }
     
-    let mut r = if let Some(s) = opts.seed {
-        rand::rngs::SmallRng::seed_from_u64(s)
+    let mut seed = match (opts.seed, &opts.seed_string) {
+        (Some(_), Some(_)) => anyhow::bail!("--seed and --seed-string are mutually exclusive"),
+        (Some(s), None) => Some(s),
+        (None, Some(s)) => Some(fnv1a(s.as_bytes())),
+        (None, None) => None,
+    };
+    if opts.print_seed {
+        let s = *seed.get_or_insert_with(rand::random);
+        eprintln!("seed={}", s);
+    }
+
+    if let Some(threads) = opts.threads {
+        if threads < 1 {
+            anyhow::bail!("--threads must be at least 1");
+        }
+        if opts.num_samples.is_none() || opts.binary_format.is_none() {
+            anyhow::bail!("--threads requires -n and --binary-format");
+        }
+        if opts.cumulative || opts.ar1.is_some() || opts.smooth.is_some() || opts.diff || opts.quasirandom || opts.skip.is_some() || opts.thin.is_some() || opts.block.is_some() {
+            anyhow::bail!("--threads cannot be used with --cumulative, --ar1, --smooth, --diff, --quasirandom, --skip, --thin or --block");
+        }
+        if opts.stats || opts.histogram.is_some() || opts.report_discards || opts.n_effective || opts.rate.is_some() || flush_every.is_some() {
+            anyhow::bail!("--threads cannot be used with --stats, --histogram, --report-discards, --n-effective, --rate or --flush-every");
+        }
+    }
+
+    let rng_bytes = std::rc::Rc::new(std::cell::Cell::new(0));
+    let r = make_rng(opts.rng, seed);
+    let r : Box<dyn rand::RngCore> = if opts.n_effective {
+        Box::new(CountingRng { inner: r, bytes: rng_bytes.clone() })
     } else {
-        rand::rngs::SmallRng::from_entropy()
+        r
     };
+    let mut dither_rng = make_rng(opts.rng, seed.map(|s| !s));
 
-    let mut c : f64 = 0.0;
-    let mut counter : u64 = 0;
-    loop {
-        if let Some(limit) = opts.num_samples {
-            if counter >= limit {
-                break;
+    if opts.json && opts.binary_format.is_some() {
+        anyhow::bail!("--json and --binary-format are mutually exclusive");
+    }
+    if opts.hex_float && opts.scientific {
+        anyhow::bail!("--hex-float and --scientific are mutually exclusive");
+    }
+    if let Some(digits) = opts.significant {
+        if digits < 1 {
+            anyhow::bail!("--significant must be at least 1");
+        }
+        if opts.hex_float || opts.scientific || opts.precision.is_some() {
+            anyhow::bail!("--significant cannot be combined with --hex-float, --scientific or --precision");
+        }
+    }
+    if opts.index && (opts.binary_format.is_some() || opts.json || opts.histogram.is_some()) {
+        anyhow::bail!("--index cannot be combined with --binary-format, --json or --histogram");
+    }
+    if opts.hex_float && (opts.binary_format.is_some() || opts.json || opts.histogram.is_some()) {
+        anyhow::bail!("--hex-float cannot be combined with --binary-format, --json or --histogram");
+    }
+    let json_array = opts.json && (opts.columns > 1 || matches!(sampler, Sampler::Vector(_)));
+    let delay = match opts.delay {
+        Some(delay) => {
+            if !(delay >= 0.0 && delay.is_finite()) {
+                anyhow::bail!("--delay must be a non-negative number of seconds, got {}", delay);
             }
+            Some(std::time::Duration::try_from_secs_f64(delay).map_err(|_| anyhow::anyhow!("--delay is too large, got {:e}", delay))?)
+        }
+        None => None,
+    };
+    if opts.sample_rate.is_some() && !opts.wav {
+        anyhow::bail!("--sample-rate requires --wav");
+    }
+    let wav_header = if opts.wav {
+        if opts.append || params.is_some() || opts.block_marker.is_some() {
+            anyhow::bail!("--wav cannot be used with --append, --params-stdin or --block-marker");
+        }
+        let (n, format) = match (opts.num_samples, opts.binary_format) {
+            (Some(n), Some(format)) => (n, format),
+            _ => anyhow::bail!("--wav requires -n and --binary-format"),
+        };
+        let channels = match sampler {
+            Sampler::Scalar(_) => opts.columns,
+            Sampler::Vector(ref d) => d.dimensions(),
+        };
+        Some((format, channels, n))
+    } else {
+        None
+    };
+
+    // Opened only after everything is validated, so that an error does not truncate the file,
+    // which may even be the input of the distribution
+    let so : Box<dyn Write> = match opts.output {
+        Some(ref path) => {
+            let f = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(opts.append)
+                .truncate(!opts.append)
+                .open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            Box::new(f)
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut so = std::io::BufWriter::with_capacity(opts.buffer_size, so);
+    if let Some((format, channels, n)) = wav_header {
+        write_wav_header(&mut so, format, channels, opts.sample_rate.unwrap_or(44100), n)?;
+    }
+    let delimiter = if opts.json {
+        ","
+    } else {
+        opts.delimiter.as_deref().unwrap_or(if opts.csv { "," } else { " " })
+    };
+
+    let line_ending = if opts.crlf { "\r\n" } else { "\n" };
+
+    let transform = Transform {
+        abs: opts.abs,
+        exponentiate,
+        exp_base: opts.exp_base,
+        scale: opts.scale,
+        offset: opts.offset,
+        round: opts.round,
+        modulo: opts.modulo,
+        below: opts.discard_below,
+        above: opts.discard_above,
+        out_of_range: if opts.clamp {
+            OutOfRange::Clamp
+        } else if opts.reflect {
+            OutOfRange::Keep
+        } else {
+            OutOfRange::Discard
+        },
+        drop_nonfinite: opts.drop_nonfinite,
+        temper: opts.temper.unwrap_or(0.0),
+    };
+
+    if let Some(delay) = delay {
+        std::thread::sleep(delay);
+    }
+
+    if let (Some(threads), Some(distribution), Some(n), Some(format)) = (opts.threads, thread_distribution, opts.num_samples, opts.binary_format) {
+        let num_blocks = n.div_ceil(THREAD_BLOCK_ROWS);
+        // All threads share the key, so that a block does not depend on the thread generating it
+        let philox_key = match opts.rng {
+            RngAlgorithm::Philox => Some(seed.unwrap_or_else(rand::random)),
+            _ => None,
+        };
+        let mut receivers = vec![];
+        for t in 0..threads {
+            let (tx, rx) = std::sync::mpsc::sync_channel::<anyhow::Result<Vec<u8>>>(2);
+            receivers.push(rx);
+            let distribution = distribution.clone();
+            let transform = transform.clone();
+            let (antithetic, columns, algorithm, reflect_values, rounding, show_latent) = (opts.antithetic, opts.columns, opts.rng, opts.reflect, opts.rounding, opts.show_latent);
+            let zero_inflate = opts.zero_inflate;
+            let (lo, hi) = (opts.discard_below, opts.discard_above);
+            let seed = seed.map(|s| s.wrapping_add(t as u64));
+            std::thread::spawn(move || {
+                let result = (|| -> anyhow::Result<()> {
+                    let sampler = make_sampler(distribution, antithetic, false, columns, show_latent, zero_inflate)?;
+                    let mut stream = SampleStream::new(sampler, make_rng(algorithm, seed), transform).columns(columns);
+                    let mut dither_rng = make_rng(algorithm, seed.map(|s| !s));
+                    let row_len = stream.row_len();
+                    for block in (t as u64..num_blocks).step_by(threads) {
+                        if let Some(key) = philox_key {
+                            stream.restart(philox_for_block(key, block));
+                            dither_rng = philox_for_block(!key, block);
+                        }
+                        let rows = THREAD_BLOCK_ROWS.min(n - block * THREAD_BLOCK_ROWS) as usize;
+                        let mut buf = Vec::with_capacity(rows * row_len * 8);
+                        for (i, x) in stream.by_ref().take(rows * row_len).enumerate() {
+                            let x = if reflect_values { reflect(x, lo, hi) } else { x };
+                            check_range(x, (block * THREAD_BLOCK_ROWS) as usize * row_len + i, row_len, assert_range)?;
+                            let x = match map_ranges {
+                                Some((from, to)) => map_range(x, from, to),
+                                None => x,
+                            };
+                            let x = match rounding {
+                                Some(r) => apply_rounding(x, r, &mut *dither_rng),
+                                None => x,
+                            };
+                            write_binary(&mut buf, &format, x)?;
+                        }
+                        if stream.gave_up() {
+                            anyhow::bail!("{} samples in a row were discarded, -L/-H limits or --drop-nonfinite are likely wrong", stream.discarded());
+                        }
+                        if tx.send(Ok(buf)).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(())
+                })();
+                if let Err(e) = result {
+                    let _ = tx.send(Err(e));
+                }
+            });
+        }
+        for block in 0..num_blocks {
+            let buf = receivers[(block % threads as u64) as usize].recv()??;
+            so.write_all(&buf)?;
         }
-        let mut x = d.sample(&mut r);
+        so.flush()?;
+        return Ok(());
+    }
 
-        if opts.exponentiate { x = x.exp(); }
+    let mut stream = SampleStream::new(sampler, r, transform).columns(opts.columns);
+    let row_len = stream.row_len();
+    let skip = opts.skip.unwrap_or(0);
+    let thin = opts.thin.unwrap_or(1);
+    let num_values = match opts.num_samples {
+        Some(_) if params.is_some() => usize::MAX,
+        Some(n) => usize::try_from(n.saturating_mul(thin).saturating_add(skip).saturating_mul(row_len as u64)).unwrap_or(usize::MAX),
+        None => usize::MAX,
+    };
 
-        if let Some(limit) = opts.discard_below {
-            if x < limit {
-                continue;
+    let mut c : Vec<f64> = vec![0.0; row_len];
+    let mut diff_prev : Option<Vec<f64>> = if opts.diff { Some(vec![0.0; row_len]) } else { None };
+    let mut moving_averages : Option<Vec<MovingAverage>> = opts.smooth.map(|w| (0..row_len).map(|_| MovingAverage::new(w)).collect());
+    let rate = opts.rate.filter(|&rate| rate != 0.0);
+    let start_time = std::time::Instant::now();
+    let mut counter : u64 = 0;
+    let mut stats = Stats::default();
+    let mut histogram_values : Option<Vec<f64>> = opts.histogram.map(|_| vec![]);
+    let mut equalized = if opts.equalize {
+        let mut values : Vec<f64> = stream.by_ref().take(num_values).collect();
+        for column in 0..row_len {
+            let mut column_values : Vec<f64> = values.iter().copied().skip(column).step_by(row_len).collect();
+            equalize(&mut column_values);
+            for (x, y) in values.iter_mut().skip(column).step_by(row_len).zip(column_values) {
+                *x = y;
             }
         }
-        if let Some(limit) = opts.discard_above {
-            if x > limit {
-                continue;
+        Some(values.into_iter())
+    } else {
+        None
+    };
+    let mut next_index = 0;
+    while next_index < num_values {
+        let x = match equalized {
+            Some(ref mut values) => values.next(),
+            None => stream.next(),
+        };
+        let x = match x {
+            Some(x) => x,
+            None => break,
+        };
+        let i = next_index;
+        next_index += 1;
+        let column = i % row_len;
+        let x = match moving_averages {
+            Some(ref mut m) => m[column].push(x),
+            None => x,
+        };
+        let acc = &mut c[column];
+        match opts.ar1 {
+            Some(rho) if i >= row_len => *acc = rho * *acc + (1.0 - rho * rho).sqrt() * x,
+            _ => *acc += x,
+        }
+        if opts.reflect {
+            *acc = reflect(*acc, opts.discard_below, opts.discard_above);
+        } else if opts.clamp {
+            *acc = clamp(*acc, opts.discard_below, opts.discard_above);
+        }
+        check_range(*acc, i, row_len, assert_range)?;
+        let mut y = *acc;
+        if let Some(ref mut prev) = diff_prev {
+            let d = y - prev[column];
+            prev[column] = y;
+            y = d;
+        }
+        let row = (i / row_len) as u64;
+        if row < skip || !(row - skip).is_multiple_of(thin) {
+            if column + 1 == row_len && ! opts.cumulative && opts.ar1.is_none() { c.fill(0.0); }
+            continue;
+        }
+        if opts.stats { stats.add(y); }
+
+        if let Some(ref mut values) = histogram_values {
+            values.push(y);
+        } else if let Some(ref format) = opts.binary_format {
+            let y = match map_ranges {
+                Some((from, to)) => map_range(y, from, to),
+                None => y,
+            };
+            let y = match opts.rounding {
+                Some(r) => apply_rounding(y, r, &mut *dither_rng),
+                None => y,
+            };
+            write_binary(&mut so, format, y)?;
+        } else {
+            if column == 0 && opts.index { write!(so, "{}{}", counter, delimiter)?; }
+            if column == 0 && json_array { write!(so, "[")?; }
+            if column > 0 { write!(so, "{}", delimiter)?; }
+            if let Some(ref labels) = labels {
+                let label = match labels.get(y as usize) {
+                    Some(label) if y >= 0.0 && y.fract() == 0.0 => label,
+                    _ => anyhow::bail!("No label for sample value {}", y),
+                };
+                if opts.json {
+                    write_json_string(&mut so, label)?;
+                } else {
+                    write!(so, "{}", label)?;
+                }
+            } else if opts.json && !y.is_finite() {
+                write!(so, "null")?;
+            } else if opts.hex_float {
+                write!(so, "{}", hex_float(y))?;
+            } else if opts.scientific {
+                write!(so, "{:.*e}", precision, y)?;
+            } else if let Some(digits) = opts.significant {
+                write!(so, "{}", significant(y, digits))?;
+            } else {
+                write!(so, "{:.*}", precision, y)?;
+            }
+            if column + 1 == row_len {
+                if json_array { write!(so, "]")?; }
+                write!(so, "{}", line_ending)?;
             }
         }
 
-        c += x;
-        match opts.binary_format {
-            None => writeln!(so, "{:.*}", opts.precision, c)?,
-            Some(BinaryFormat::F32LE) => so.write_f32::<LE>(c as f32)?,
-            Some(BinaryFormat::F32BE) => so.write_f32::<BE>(c as f32)?,
-            Some(BinaryFormat::F64LE) => so.write_f64::<LE>(c)?,
-            Some(BinaryFormat::F64BE) => so.write_f64::<BE>(c)?,
-            Some(BinaryFormat::S8) => so.write_i8(c as i8)?,
-            Some(BinaryFormat::U8) => so.write_u8(c as u8)?,
-            Some(BinaryFormat::S16LE) => so.write_i16::<LE>(c as i16)?,
-            Some(BinaryFormat::S16BE) => so.write_i16::<BE>(c as i16)?,
-            Some(BinaryFormat::U16LE) => so.write_u16::<LE>(c as u16)?,
-            Some(BinaryFormat::U16BE) => so.write_u16::<BE>(c as u16)?,
-            Some(BinaryFormat::S32LE) => so.write_i32::<LE>(c as i32)?,
-            Some(BinaryFormat::S32BE) => so.write_i32::<BE>(c as i32)?,
-            Some(BinaryFormat::U32LE) => so.write_u32::<LE>(c as u32)?,
-            Some(BinaryFormat::U32BE) => so.write_u32::<BE>(c as u32)?,
-            Some(BinaryFormat::S64LE) => so.write_i64::<LE>(c as i64)?,
-            Some(BinaryFormat::S64BE) => so.write_i64::<BE>(c as i64)?,
-            Some(BinaryFormat::U64LE) => so.write_u64::<LE>(c as u64)?,
-            Some(BinaryFormat::U64BE) => so.write_u64::<BE>(c as u64)?,
-        }
-        
-        if ! opts.cumulative { c = 0.0; }
+        if column + 1 < row_len {
+            continue;
+        }
+        if ! opts.cumulative && opts.ar1.is_none() { c.fill(0.0); }
         counter = counter.wrapping_add(1);
+
+        if let Some(block) = opts.block {
+            if counter.is_multiple_of(block) {
+                if opts.binary_format.is_some() {
+                    so.write_all(&block_marker)?;
+                } else {
+                    write!(so, "{}", line_ending)?;
+                }
+            }
+        }
+
+        if let Some(ref mut params) = params {
+            if counter.is_multiple_of(params_per_line) {
+                match params.next_distribution()? {
+                    Some(d) => {
+                        // Each line may switch between integer and fractional values
+                        precision = opts.precision.unwrap_or_else(|| default_precision(&opts, &d));
+                        make_sampler(d, opts.antithetic, opts.quasirandom, opts.columns, opts.show_latent, opts.zero_inflate)
+                            .and_then(|s| stream.set_sampler(s))
+                            .with_context(|| format!("Invalid parameters on stdin line {}", params.line_number))?
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        if let Some(n) = flush_every {
+            if counter.is_multiple_of(n) {
+                so.flush()?;
+            }
+        }
+
+        if let Some(rate) = rate {
+            let due = std::time::Duration::try_from_secs_f64(counter as f64 / rate).ok()
+                .and_then(|elapsed| start_time.checked_add(elapsed))
+                .ok_or_else(|| anyhow::anyhow!("--rate is too low to schedule row {}", counter))?;
+            let now = std::time::Instant::now();
+            if due > now {
+                so.flush()?;
+                std::thread::sleep(due - now);
+            }
+        }
+    }
+    if stream.gave_up() {
+        anyhow::bail!("{} samples in a row were discarded, -L/-H limits or --drop-nonfinite are likely wrong", stream.discarded());
+    }
+    if let (Some(bins), Some(values)) = (opts.histogram, histogram_values) {
+        print_histogram(&mut so, &values, bins, precision, line_ending)?;
+    }
+    if opts.report_discards {
+        so.flush()?;
+        let discarded = stream.discarded();
+        let total = counter + discarded;
+        let rate = if total > 0 { discarded as f64 / total as f64 } else { 0.0 };
+        eprintln!("accepted={} discarded={} rejection_rate={}", counter, discarded, rate);
+    }
+    if opts.n_effective {
+        so.flush()?;
+        let draws = stream.accepted() + stream.discarded();
+        let ratio = if draws > 0 { counter as f64 / draws as f64 } else { 0.0 };
+        eprintln!("draws={} emitted={} ratio={} rng_bytes={}", draws, counter, ratio, rng_bytes.get());
+    }
+    if opts.stats {
+        so.flush()?;
+        eprintln!("{}", stats);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn reflect_about_limits() {
+        assert_eq!(reflect(0.5, Some(0.0), Some(1.0)), 0.5);
+        assert_eq!(reflect(-0.25, Some(0.0), Some(1.0)), 0.25);
+        assert_eq!(reflect(1.25, Some(0.0), Some(1.0)), 0.75);
+        assert_eq!(reflect(2.25, Some(0.0), Some(1.0)), 0.25);
+        assert_eq!(reflect(-1.25, Some(0.0), Some(1.0)), 0.75);
+        assert_eq!(reflect(-3.0, Some(-1.0), None), 1.0);
+        assert_eq!(reflect(5.0, None, Some(2.0)), -1.0);
+        assert_eq!(reflect(1.0, None, Some(2.0)), 1.0);
+    }
+
+    #[test]
+    fn scientific_keeps_integer_digits() {
+        let precision = |args: &[&str]| {
+            let opts = <Opts as argh::FromArgs>::from_args(&["random-distributions-cli"], args).unwrap();
+            default_precision(&opts, opts.distribution.as_ref().unwrap())
+        };
+        assert_eq!(format!("{:.*e}", precision(&["-E", "poisson", "12345"]), 12345.0).parse::<f64>().unwrap(), 12345.0);
+        assert_eq!(precision(&["poisson", "12345"]), 0);
+    }
+
+    #[test]
+    fn params_stdin_precision_per_line() {
+        let opts = <Opts as argh::FromArgs>::from_args(&["random-distributions-cli"], &["--params-stdin", "constant"]).unwrap();
+        let precision = |line: &[&str]| default_precision(&opts, &parse_args("constant", line).unwrap());
+        assert_eq!(precision(&["1"]), 0);
+        assert_eq!(precision(&["1.5"]), 10);
+        assert_eq!(precision(&["2"]), 0);
+
+        let opts = <Opts as argh::FromArgs>::from_args(&["random-distributions-cli"], &["--params-stdin", "mixture"]).unwrap();
+        assert_eq!(default_precision(&opts, &parse_args("mixture", &["1:constant:0.25"]).unwrap()), 10);
+    }
+
+    #[test]
+    fn assert_range_after_reflect() {
+        let range = Some((0.0, 1.0));
+        for (i, &x) in [-0.25, 0.5, 1.25, 2.5].iter().enumerate() {
+            check_range(reflect(x, Some(0.0), Some(1.0)), i, 1, range).unwrap();
+        }
+        assert!(check_range(1.25, 0, 1, range).is_err());
+    }
+
+    #[test]
+    fn round_step_must_be_positive_and_finite() {
+        assert!(check_round_step(0.5).is_ok());
+        for step in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(check_round_step(step).is_err(), "{}", step);
+        }
+    }
+
+    #[test]
+    fn clamp_to_limits() {
+        assert_eq!(clamp(0.5, Some(0.0), Some(1.0)), 0.5);
+        assert_eq!(clamp(-0.25, Some(0.0), Some(1.0)), 0.0);
+        assert_eq!(clamp(1.25, Some(0.0), Some(1.0)), 1.0);
+        assert_eq!(clamp(-3.0, Some(-1.0), None), -1.0);
+        assert_eq!(clamp(5.0, Some(-1.0), None), 5.0);
+        assert_eq!(clamp(5.0, None, Some(2.0)), 2.0);
+    }
+
+    /// Write three samples of a constant distribution in the specified format
+    fn constant_stream_bytes(value: f64, format: BinaryFormat) -> Vec<u8> {
+        let sampler = build_sampler(Distributions::Constant(random_distributions_cli::Constant { value })).unwrap();
+        let rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut buf = vec![];
+        for x in SampleStream::new(sampler, rng, Transform::default()).take(3) {
+            write_binary(&mut buf, &format, x).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        use byteorder::ReadBytesExt;
+        use BinaryFormat::*;
+
+        fn read_all<T>(buf: &[u8], size: usize, read: impl Fn(&mut &[u8]) -> std::io::Result<T>) -> Vec<T> {
+            assert_eq!(buf.len(), 3 * size);
+            let mut r = buf;
+            let v = (0..3).map(|_| read(&mut r).unwrap()).collect();
+            assert!(r.is_empty());
+            v
+        }
+
+        let subnormal = f64::from_bits(1);
+        for x in [1.5, -0.0, subnormal, f64::MIN_POSITIVE, f64::MAX, 9007199254740992.0] {
+            assert_eq!(read_all(&constant_stream_bytes(x, F64LE), 8, |r| r.read_f64::<LE>()), [x; 3]);
+            assert_eq!(read_all(&constant_stream_bytes(x, F64BE), 8, |r| r.read_f64::<BE>()), [x; 3]);
+        }
+        for x in [1.5, -0.0, f32::MIN_POSITIVE as f64, f32::MAX as f64, 16777216.0] {
+            assert_eq!(read_all(&constant_stream_bytes(x, F32LE), 4, |r| r.read_f32::<LE>()), [x as f32; 3]);
+            assert_eq!(read_all(&constant_stream_bytes(x, F32BE), 4, |r| r.read_f32::<BE>()), [x as f32; 3]);
+        }
+        // Signed zero survives in floating point formats
+        assert!(read_all(&constant_stream_bytes(-0.0, F32BE), 4, |r| r.read_f32::<BE>())[0].is_sign_negative());
+        assert!(read_all(&constant_stream_bytes(-0.0, F64LE), 8, |r| r.read_f64::<LE>())[0].is_sign_negative());
+        // f64 subnormals underflow to zero in f32
+        assert_eq!(read_all(&constant_stream_bytes(subnormal, F32LE), 4, |r| r.read_f32::<LE>()), [0.0; 3]);
+        assert_eq!(constant_stream_bytes(1.0, F32BE), [0x3f, 0x80, 0, 0].repeat(3));
+        assert_eq!(constant_stream_bytes(1.0, F32LE), [0, 0, 0x80, 0x3f].repeat(3));
+
+        // Integer formats at the boundaries of their ranges; -0.0 is plain 0
+        assert_eq!(constant_stream_bytes(255.0, U8), [255; 3]);
+        assert_eq!(constant_stream_bytes(-0.0, U8), [0; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(-128.0, S8), 1, |r| r.read_i8()), [-128; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(65535.0, U16LE), 2, |r| r.read_u16::<LE>()), [65535; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(258.0, U16BE), 2, |r| r.read_u16::<BE>()), [258; 3]);
+        assert_eq!(constant_stream_bytes(258.0, U16BE), [1, 2].repeat(3));
+        assert_eq!(constant_stream_bytes(258.0, U16LE), [2, 1].repeat(3));
+        assert_eq!(read_all(&constant_stream_bytes(-32768.0, S16LE), 2, |r| r.read_i16::<LE>()), [i16::MIN; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(32767.0, S16BE), 2, |r| r.read_i16::<BE>()), [i16::MAX; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(4294967295.0, U32LE), 4, |r| r.read_u32::<LE>()), [u32::MAX; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(16909060.0, U32BE), 4, |r| r.read_u32::<BE>()), [0x01020304; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(-2147483648.0, S32LE), 4, |r| r.read_i32::<LE>()), [i32::MIN; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(2147483647.0, S32BE), 4, |r| r.read_i32::<BE>()), [i32::MAX; 3]);
+        // 2^53 + 2 is exactly representable in f64
+        assert_eq!(read_all(&constant_stream_bytes(9007199254740994.0, U64LE), 8, |r| r.read_u64::<LE>()), [9007199254740994; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(18446744073709551615.0, U64BE), 8, |r| r.read_u64::<BE>()), [u64::MAX; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(-9223372036854775808.0, S64LE), 8, |r| r.read_i64::<LE>()), [i64::MIN; 3]);
+        assert_eq!(read_all(&constant_stream_bytes(-9007199254740994.0, S64BE), 8, |r| r.read_i64::<BE>()), [-9007199254740994; 3]);
+    }
+
+    #[test]
+    fn binary_clamping() {
+        use byteorder::ReadBytesExt;
+        fn roundtrip_f32(x: f64) -> f32 {
+            let mut buf = vec![];
+            write_binary(&mut buf, &BinaryFormat::F32LE, x).unwrap();
+            (&buf[..]).read_f32::<LE>().unwrap()
+        }
+        fn roundtrip_u8(x: f64) -> u8 {
+            let mut buf = vec![];
+            write_binary(&mut buf, &BinaryFormat::U8, x).unwrap();
+            buf[0]
+        }
+        fn roundtrip_s16(x: f64) -> i16 {
+            let mut buf = vec![];
+            write_binary(&mut buf, &BinaryFormat::S16BE, x).unwrap();
+            (&buf[..]).read_i16::<BE>().unwrap()
+        }
+        fn roundtrip_u64(x: f64) -> u64 {
+            let mut buf = vec![];
+            write_binary(&mut buf, &BinaryFormat::U64LE, x).unwrap();
+            (&buf[..]).read_u64::<LE>().unwrap()
+        }
+
+        let mut buf = vec![];
+        write_binary(&mut buf, &BinaryFormat::F64BE, 0.1).unwrap();
+        assert_eq!((&buf[..]).read_f64::<BE>().unwrap(), 0.1);
+
+        assert_eq!(roundtrip_f32(1.5), 1.5);
+        assert_eq!(roundtrip_f32(1e300), f32::MAX);
+        assert_eq!(roundtrip_f32(-1e300), f32::MIN);
+        assert_eq!(roundtrip_f32(f64::INFINITY), f32::INFINITY);
+        assert!(roundtrip_f32(f64::NAN).is_nan());
+
+        assert_eq!(roundtrip_u8(3.7), 3);
+        assert_eq!(roundtrip_u8(300.0), 255);
+        assert_eq!(roundtrip_u8(-5.0), 0);
+        assert_eq!(roundtrip_u8(f64::INFINITY), 255);
+        assert_eq!(roundtrip_s16(-3.7), -3);
+        assert_eq!(roundtrip_s16(-1e9), i16::MIN);
+        assert_eq!(roundtrip_s16(f64::NEG_INFINITY), i16::MIN);
+        assert_eq!(roundtrip_u64(1e30), u64::MAX);
+
+        assert!(write_binary(&mut vec![], &BinaryFormat::S32LE, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn map_range_to_format() {
+        let to = BinaryFormat::S16LE.integer_range().unwrap();
+        assert_eq!(map_range(-4.0, (-4.0, 4.0), to), -32768.0);
+        assert_eq!(map_range(4.0, (-4.0, 4.0), to), 32767.0);
+        assert_eq!(map_range(100.0, (-4.0, 4.0), to), 32767.0);
+        assert_eq!(map_range(0.5, (0.0, 1.0), BinaryFormat::U8.integer_range().unwrap()), 127.5);
+        assert!(BinaryFormat::F32LE.integer_range().is_none());
+        assert_eq!(parse_range("-1, 2").unwrap(), (-1.0, 2.0));
+        assert!(parse_range("2,1").is_err());
+    }
+
+    #[test]
+    fn equalize_ranks() {
+        let mut v = [3.0, -1.0, 7.0, 3.0];
+        equalize(&mut v);
+        assert_eq!(v, [0.625, 0.25, 1.0, 0.625]);
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        assert_eq!(apply_rounding(-2.7, Rounding::Trunc, &mut rng), -2.0);
+        assert_eq!(apply_rounding(-2.7, Rounding::Nearest, &mut rng), -3.0);
+        assert_eq!(apply_rounding(2.5, Rounding::Nearest, &mut rng), 3.0);
+        assert_eq!(apply_rounding(4.0, Rounding::Stochastic, &mut rng), 4.0);
+        let n = 100000;
+        let sum : f64 = (0..n).map(|_| apply_rounding(2.25, Rounding::Stochastic, &mut rng)).sum();
+        assert!((sum / n as f64 - 2.25).abs() < 0.01);
+    }
+
+    #[test]
+    fn moving_average_partial_window() {
+        let mut m = MovingAverage::new(3);
+        let v : Vec<f64> = [3.0, 6.0, 9.0, 0.0, 0.0, 0.0].iter().map(|&x| m.push(x)).collect();
+        assert_eq!(v, [3.0, 4.5, 6.0, 5.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(parse_hex("ff00A5").unwrap(), [0xff, 0x00, 0xa5]);
+        assert_eq!(parse_hex("").unwrap(), []);
+        assert!(parse_hex("f").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[test]
+    fn significant_digits() {
+        assert_eq!(significant(1.23456, 3), "1.23");
+        assert_eq!(significant(-0.00123456, 2), "-0.0012");
+        assert_eq!(significant(9.996, 3), "10.0");
+        assert_eq!(significant(123456.0, 2), "120000");
+        assert_eq!(significant(-987654.0, 3), "-988000");
+        assert_eq!(significant(1.23e25, 3), "12300000000000000000000000");
+        assert_eq!(significant(0.0, 3), "0.00");
+        assert_eq!(significant(7.0, 1), "7");
+    }
+
+    #[test]
+    fn hex_float_format() {
+        assert_eq!(hex_float(1.0), "0x1p+0");
+        assert_eq!(hex_float(0.5), "0x1p-1");
+        assert_eq!(hex_float(-3.0), "-0x1.8p+1");
+        assert_eq!(hex_float(0.1), "0x1.999999999999ap-4");
+        assert_eq!(hex_float(0.0), "0x0p+0");
+        assert_eq!(hex_float(-0.0), "-0x0p+0");
+        assert_eq!(hex_float(f64::from_bits(1)), "0x0.0000000000001p-1022");
+        assert_eq!(hex_float(f64::MAX), "0x1.fffffffffffffp+1023");
+        assert_eq!(hex_float(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn stats_welford() {
+        let mut s = Stats::default();
+        for x in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            s.add(x);
+        }
+        assert_eq!(s.count, 8);
+        assert_eq!(s.min, 2.0);
+        assert_eq!(s.max, 9.0);
+        assert_eq!(s.mean, 5.0);
+        assert!((s.std_dev() - (32.0f64/7.0).sqrt()).abs() < 1e-12);
+
+        assert_eq!(Stats::default().to_string(), "count=0 min=NaN max=NaN mean=NaN stddev=NaN");
+    }
+}